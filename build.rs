@@ -0,0 +1,44 @@
+use std::{env, fs, path::{Path, PathBuf}};
+
+//only these files are embedded, the .vectr sources next to the pngs are not needed at runtime
+const EMBEDDED_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "ron", "fnt"];
+
+/**
+ * With the embedded-assets feature, every asset in textures/ is put into the binary with include_bytes!, see src/assets.rs.
+ * The generated file maps the path relative to the crate root (e.g. "textures/sprites.ron") to the bytes of the file
+ */
+fn main(){
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-changed=textures");
+
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut files = Vec::new();
+    collect_files(&root.join("textures"), &mut files);
+    files.sort();
+
+    let mut generated = String::from("pub static EMBEDDED_ASSETS: &[(&str, &[u8])] = &[\n");
+    for file in files {
+        let relative = file.strip_prefix(&root).unwrap();
+        let key = relative.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        generated.push_str(&format!("    ({:?}, include_bytes!({:?})),\n", key, file.display().to_string()));
+    }
+    generated.push_str("];\n");
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("embedded_assets.rs");
+    fs::write(out, generated).unwrap();
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>){
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        println!("cargo:rerun-if-changed={}", path.display());
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.extension().map_or(false, |extension| EMBEDDED_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())) {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::{borrow::Cow, io, path::{Path, Component}, time::SystemTime, fs};
+
+
+#[cfg(feature = "embedded-assets")]
+mod embedded{
+    //generated by build.rs
+    include!(concat!(env!("OUT_DIR"), "/embedded_assets.rs"));
+}
+
+
+/**
+ * true if the assets were put into the binary (feature embedded-assets). In that case nothing is read from disk and hot reloading is not possible
+ */
+pub const EMBEDDED: bool = cfg!(feature = "embedded-assets");
+
+
+/**
+ * Reads an asset, either from the binary or from the disk (relative to the working directory). Every asset (sprite manifest, images, fonts) should be loaded with this,
+ * so release builds can embed them without any other changes
+ */
+pub fn read(path: &Path) -> io::Result<Cow<'static, [u8]>>{
+    #[cfg(feature = "embedded-assets")]
+    {
+        let key = normalize(path);
+        return match embedded::EMBEDDED_ASSETS.iter().find(|(name, _)| *name == key) {
+            Some((_, bytes)) => Ok(Cow::Borrowed(*bytes)),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not embedded into the binary", key))),
+        };
+    }
+    #[cfg(not(feature = "embedded-assets"))]
+    fs::read(path).map(Cow::Owned)
+}
+
+pub fn read_to_string(path: &Path) -> io::Result<String>{
+    let bytes = read(path)?;
+    String::from_utf8(bytes.into_owned()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/**
+ * when the file on disk was changed the last time, used to find out which assets need to be reloaded. None for embedded assets, since they never change
+ */
+pub fn modified(path: &Path) -> Option<SystemTime>{
+    if EMBEDDED {
+        return None;
+    }
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/**
+ * "textures/./fonts/../sprites.ron" -> "textures/sprites.ron", the keys in the embedded table look like this
+ */
+#[allow(dead_code)]
+fn normalize(path: &Path) -> String{
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir => { parts.pop(); },
+            _ => (),
+        }
+    }
+    parts.join("/")
+}
@@ -1,2 +1,12 @@
 pub const WINDOW_INIT_X: u32 = 1600;
-pub const WINDOW_INIT_Y: u32 = 860;
\ No newline at end of file
+pub const WINDOW_INIT_Y: u32 = 860;
+pub const SPRITE_MANIFEST_PATH: &str = "textures/sprites.ron";
+//the color everything is drawn on top of (r, g, b, a). Shared by the wgpu renderer and the software renderer
+pub const BACKGROUND_COLOR: [f64; 4] = [0.2, 0.2, 0.2, 1.0];
+//how bright the world is without any lights (r, g, b), 1.0 is fully lit. Point lights are added on top of it, see PointLight
+pub const AMBIENT_LIGHT: [f32; 3] = [0.22, 0.2, 0.26];
+//screenshots are written into this directory (relative to the working directory)
+pub const SCREENSHOT_DIRECTORY: &str = "screenshots";
+//BMFont description of the default font, its atlas has to be in the sprite manifest under FONT_SPRITE_NAME
+pub const FONT_PATH: &str = "textures/fonts/dejavu_sans_32.fnt";
+pub const FONT_SPRITE_NAME: &str = "font_dejavu_sans";
\ No newline at end of file
@@ -2,4 +2,7 @@ pub mod controller_input;
 pub mod controller;
 pub mod game_state;
 pub(crate) mod button_constants;
-mod button_mapping;
\ No newline at end of file
+mod button_mapping;
+pub(crate) mod debug_overlay;
+pub mod viewport_policy;
+pub mod input_script;
\ No newline at end of file
@@ -11,4 +11,12 @@ pub(crate) const CTRL: usize = 8;
 pub(crate) const J_BUTTON: usize = 9;
 pub(crate) const L_BUTTON: usize = 10;
 pub(crate) const I_BUTTON: usize = 11;
-pub(crate) const O_BUTTON: usize = 12;
\ No newline at end of file
+pub(crate) const O_BUTTON: usize = 12;
+pub(crate) const F12_BUTTON: usize = 13;
+pub(crate) const PRINT_SCREEN: usize = 14;
+pub(crate) const F3_BUTTON: usize = 15;
+pub(crate) const F4_BUTTON: usize = 16;
+pub(crate) const F5_BUTTON: usize = 17;
+pub(crate) const F6_BUTTON: usize = 18;
+pub(crate) const F7_BUTTON: usize = 19;
+pub(crate) const F8_BUTTON: usize = 20;
@@ -4,14 +4,14 @@ use winit::event::VirtualKeyCode;
 
 use crate::model::model::Model;
 
-use super::{controller::{KeyboundFunction, half_screen_width_ingame_point5times, no_action, half_screen_width_ingame_2times, place_debug_object_action, half_screen_width_ingame_regular, simulate_mouse_wheel_up, simulate_mouse_wheel_down, camera_up_action, camera_right_action, camera_down_action, camera_left_action, camera_up_action_released, camera_right_action_released, camera_down_action_released, camera_left_action_released}, button_constants::{W_BUTTON, D_BUTTON, S_BUTTON, A_BUTTON, MOUSE_LEFT, MOUSE_RIGHT, MOUSE_MIDDLE, SPACE_BAR, CTRL, J_BUTTON, L_BUTTON, I_BUTTON, O_BUTTON}, game_state::GameState};
+use super::{controller::{KeyboundFunction, half_screen_width_ingame_point5times, no_action, half_screen_width_ingame_2times, place_debug_object_action, half_screen_width_ingame_regular, simulate_mouse_wheel_up, simulate_mouse_wheel_down, screenshot_action, toggle_debug_overlay_action, toggle_post_processing_action, toggle_post_effect_1_action, toggle_post_effect_2_action, toggle_post_effect_3_action, toggle_post_effect_4_action, camera_up_action, camera_right_action, camera_down_action, camera_left_action, camera_up_action_released, camera_right_action_released, camera_down_action_released, camera_left_action_released}, button_constants::{W_BUTTON, D_BUTTON, S_BUTTON, A_BUTTON, MOUSE_LEFT, MOUSE_RIGHT, MOUSE_MIDDLE, SPACE_BAR, CTRL, J_BUTTON, L_BUTTON, I_BUTTON, O_BUTTON, F12_BUTTON, PRINT_SCREEN, F3_BUTTON, F4_BUTTON, F5_BUTTON, F6_BUTTON, F7_BUTTON, F8_BUTTON}, game_state::GameState};
 
 pub(crate) fn load_default_keybinds() -> Vec<(Option<KeyboundFunction>, Option<KeyboundFunction>)>{
     let mut ret :Vec<(Option<KeyboundFunction>, Option<KeyboundFunction>)>= Vec::new();
     //TODO: add a config file for bound defaults, fallback to code, if none is present
     //see button_constants.rs, to figure out how the indices represent different keys
 
-    ret.resize(13, (None,None) );
+    ret.resize(21, (None,None) );
     
     
     // Keydown , Keyup
@@ -43,6 +43,24 @@ pub(crate) fn load_default_keybinds() -> Vec<(Option<KeyboundFunction>, Option<K
     ret[I_BUTTON] = (Some(fn_pointer), None);
     let fn_pointer: KeyboundFunction = half_screen_width_ingame_2times;
     ret[O_BUTTON] = (Some(fn_pointer), None);
+    let fn_pointer: KeyboundFunction = screenshot_action;
+    ret[F12_BUTTON] = (Some(fn_pointer), None);
+    //some platforms (Windows) only report the release of the print screen key, so this is bound to keyup
+    let fn_pointer: KeyboundFunction = screenshot_action;
+    ret[PRINT_SCREEN] = (None, Some(fn_pointer));
+    let fn_pointer: KeyboundFunction = toggle_debug_overlay_action;
+    ret[F3_BUTTON] = (Some(fn_pointer), None);
+    let fn_pointer: KeyboundFunction = toggle_post_processing_action;
+    ret[F4_BUTTON] = (Some(fn_pointer), None);
+    //F5 - F8 switch the first four effects of the post processing chain
+    let fn_pointer: KeyboundFunction = toggle_post_effect_1_action;
+    ret[F5_BUTTON] = (Some(fn_pointer), None);
+    let fn_pointer: KeyboundFunction = toggle_post_effect_2_action;
+    ret[F6_BUTTON] = (Some(fn_pointer), None);
+    let fn_pointer: KeyboundFunction = toggle_post_effect_3_action;
+    ret[F7_BUTTON] = (Some(fn_pointer), None);
+    let fn_pointer: KeyboundFunction = toggle_post_effect_4_action;
+    ret[F8_BUTTON] = (Some(fn_pointer), None);
 
 
 
@@ -92,16 +110,16 @@ pub(crate) fn key_action_pressed(key: VirtualKeyCode, game_state: &Arc<RwLock<Ga
         VirtualKeyCode::Escape => (),
         VirtualKeyCode::F1 => (),
         VirtualKeyCode::F2 => (),
-        VirtualKeyCode::F3 => (),
-        VirtualKeyCode::F4 => (),
-        VirtualKeyCode::F5 => (),
-        VirtualKeyCode::F6 => (),
-        VirtualKeyCode::F7 => (),
-        VirtualKeyCode::F8 => (),
+        VirtualKeyCode::F3 => if let Some(func) = keybinds[F3_BUTTON].0 { func(game_state, model)},
+        VirtualKeyCode::F4 => if let Some(func) = keybinds[F4_BUTTON].0 { func(game_state, model)},
+        VirtualKeyCode::F5 => if let Some(func) = keybinds[F5_BUTTON].0 { func(game_state, model)},
+        VirtualKeyCode::F6 => if let Some(func) = keybinds[F6_BUTTON].0 { func(game_state, model)},
+        VirtualKeyCode::F7 => if let Some(func) = keybinds[F7_BUTTON].0 { func(game_state, model)},
+        VirtualKeyCode::F8 => if let Some(func) = keybinds[F8_BUTTON].0 { func(game_state, model)},
         VirtualKeyCode::F9 => (),
         VirtualKeyCode::F10 => (),
         VirtualKeyCode::F11 => (),
-        VirtualKeyCode::F12 => (),
+        VirtualKeyCode::F12 => if let Some(func) = keybinds[F12_BUTTON].0 { func(game_state, model)},
         VirtualKeyCode::F13 => (),
         VirtualKeyCode::F14 => (),
         VirtualKeyCode::F15 => (),
@@ -114,7 +132,7 @@ pub(crate) fn key_action_pressed(key: VirtualKeyCode, game_state: &Arc<RwLock<Ga
         VirtualKeyCode::F22 => (),
         VirtualKeyCode::F23 => (),
         VirtualKeyCode::F24 => (),
-        VirtualKeyCode::Snapshot => (),
+        VirtualKeyCode::Snapshot => if let Some(func) = keybinds[PRINT_SCREEN].0 { func(game_state, model)},
         VirtualKeyCode::Scroll => (),
         VirtualKeyCode::Pause => (),
         VirtualKeyCode::Insert => (),
@@ -260,16 +278,16 @@ pub(crate) fn key_action_released(key: VirtualKeyCode, game_state: &Arc<RwLock<G
         VirtualKeyCode::Escape => (),
         VirtualKeyCode::F1 => (),
         VirtualKeyCode::F2 => (),
-        VirtualKeyCode::F3 => (),
-        VirtualKeyCode::F4 => (),
-        VirtualKeyCode::F5 => (),
-        VirtualKeyCode::F6 => (),
-        VirtualKeyCode::F7 => (),
-        VirtualKeyCode::F8 => (),
+        VirtualKeyCode::F3 => if let Some(func) = keybinds[F3_BUTTON].1 { func(game_state, model)},
+        VirtualKeyCode::F4 => if let Some(func) = keybinds[F4_BUTTON].1 { func(game_state, model)},
+        VirtualKeyCode::F5 => if let Some(func) = keybinds[F5_BUTTON].1 { func(game_state, model)},
+        VirtualKeyCode::F6 => if let Some(func) = keybinds[F6_BUTTON].1 { func(game_state, model)},
+        VirtualKeyCode::F7 => if let Some(func) = keybinds[F7_BUTTON].1 { func(game_state, model)},
+        VirtualKeyCode::F8 => if let Some(func) = keybinds[F8_BUTTON].1 { func(game_state, model)},
         VirtualKeyCode::F9 => (),
         VirtualKeyCode::F10 => (),
         VirtualKeyCode::F11 => (),
-        VirtualKeyCode::F12 => (),
+        VirtualKeyCode::F12 => if let Some(func) = keybinds[F12_BUTTON].1 { func(game_state, model)},
         VirtualKeyCode::F13 => (),
         VirtualKeyCode::F14 => (),
         VirtualKeyCode::F15 => (),
@@ -282,7 +300,7 @@ pub(crate) fn key_action_released(key: VirtualKeyCode, game_state: &Arc<RwLock<G
         VirtualKeyCode::F22 => (),
         VirtualKeyCode::F23 => (),
         VirtualKeyCode::F24 => (),
-        VirtualKeyCode::Snapshot => (),
+        VirtualKeyCode::Snapshot => if let Some(func) = keybinds[PRINT_SCREEN].1 { func(game_state, model)},
         VirtualKeyCode::Scroll => (),
         VirtualKeyCode::Pause => (),
         VirtualKeyCode::Insert => (),
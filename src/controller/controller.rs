@@ -1,17 +1,40 @@
 
-use std::{sync::{Arc, atomic::{AtomicBool, self}, RwLock}};
+use std::{sync::{Arc, atomic::{AtomicBool, self}, RwLock}, time::{Duration, Instant}};
 
 use flume::{Receiver, Sender};
 use tokio::{join};
 use winit::event::{VirtualKeyCode, ElementState, MouseScrollDelta};
 
-use crate::{controller::{controller_input::MouseInputType, button_mapping::{load_default_keybinds, key_action_pressed, key_action_released}}, view::renderer::Vertex, model::{game_object::{debug_object::DebugObject}, model::Model}, drawable_object::{drawable_object::DrawableObject}};
+use crate::{controller::{controller_input::MouseInputType, button_mapping::{load_default_keybinds, key_action_pressed, key_action_released}}, view::{renderer::Instance, frame::{Frame, CameraUniform}, render_statistics::RenderStatistics, lighting::LightInstance, post_processing::PostEffect}, model::{game_object::{debug_object::DebugObject}, model::Model, particles::{ParticleEmitter, EmitterSettings}}, drawable_object::{drawable_object::DrawableObject, render_layer::RenderLayer, bounding_box::BoundingBox}};
 
-use super::{controller_input::ControllerInput, game_state::{GameState, CamKeyPressed}, button_constants::{MOUSE_LEFT}};
+use super::{controller_input::ControllerInput, game_state::{GameState, CamKeyPressed}, button_constants::{MOUSE_LEFT}, debug_overlay::{OverlayInput, overlay_text, overlay_instances}};
 
 use spin_sleep::LoopHelper;
 
 
+//objects are culled against the visible area grown by this fraction on every side, so the camera can move a bit before the instances need to be rebuilt
+const CULLING_MARGIN: f64 = 0.5;
+//the rates and frame times in the debug overlay change on almost every loop, so its text is only put together this often, otherwise it would be laid out again every time
+const OVERLAY_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+
+/**
+ * how many objects ended up in the instances of the last rebuild and how many were skipped, because they were outside of the culling area
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct CullingStats{
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+impl std::ops::Add for CullingStats {
+    type Output = CullingStats;
+    fn add(self, other: CullingStats) -> CullingStats {
+        CullingStats { drawn: self.drawn + other.drawn, culled: self.culled + other.culled }
+    }
+}
+
+
 pub(crate) type KeyboundFunction = fn(&Arc<RwLock<GameState>>, &Arc<Model>);
 
 pub fn handle_input_loop(thread_running: Arc<AtomicBool>, receiver: Receiver<ControllerInput>, game_state: Arc<RwLock<GameState>>, model_pointer:  Arc<Model>){
@@ -28,7 +51,17 @@ pub fn handle_input_loop(thread_running: Arc<AtomicBool>, receiver: Receiver<Con
             match input{
                 ControllerInput::MouseInput { action } => process_mouse_input(action, &game_state, &keybinds, &model_pointer),
                 ControllerInput::KeyboardInput { key, state } => process_keyboard_input(key, state, &game_state, &keybinds, &model_pointer),
-                ControllerInput::WindowResized { dimensions } => game_state.write().unwrap().window_dimensions = dimensions,
+                ControllerInput::WindowResized { dimensions } => {
+                    let mut lock = game_state.write().unwrap();
+                    let scale_factor = lock.scale_factor;
+                    lock.resize_window(dimensions, scale_factor);
+                    drop(lock);
+                    recalculate_mouse_pos(&game_state);
+                },
+                ControllerInput::ScaleFactorChanged { scale_factor, dimensions } => {
+                    game_state.write().unwrap().resize_window(dimensions, scale_factor);
+                    recalculate_mouse_pos(&game_state);
+                },
             }
 
         }else {
@@ -143,16 +176,19 @@ pub(crate) fn camera_left_action_released(game_state: &Arc<RwLock<GameState>>, _
 pub(crate) fn half_screen_width_ingame_regular(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
     let mut game_state_lock = game_state.write().unwrap();
     game_state_lock.window_dimensions_ingame = (1.0, 1.0);
+    game_state_lock.fit_view();
 }
 #[inline]
 pub(crate) fn half_screen_width_ingame_2times(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
     let mut game_state_lock = game_state.write().unwrap();
     game_state_lock.window_dimensions_ingame = (2.0, 2.0);
+    game_state_lock.fit_view();
 }
 #[inline]
 pub(crate) fn half_screen_width_ingame_point5times(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
     let mut game_state_lock = game_state.write().unwrap();
     game_state_lock.window_dimensions_ingame = (0.5, 0.5);
+    game_state_lock.fit_view();
 }
 
 #[inline]
@@ -178,10 +214,50 @@ pub(crate) fn place_debug_object_action(game_state: &Arc<RwLock<GameState>>, mod
     let lock = game_state.read().unwrap();
     let mouse_coords = lock.cursor_pos_ingame;
     let mut lock = model.game_objects.blocking_write();
-    let new_object = DebugObject::new(mouse_coords, 0);
+    let new_object = DebugObject::new_building(mouse_coords, model.sprites.id("Dwarf_BaseHouse"), 0.4, &model.sprites);
     lock.push(Box::new(new_object));
+    drop(lock);
+    model.mark_objects_changed();
+    //the building stands on the ground at the cursor, that is where the dust comes from
+    model.particles.blocking_write().add(ParticleEmitter::one_shot(mouse_coords, EmitterSettings::dust(model.sprites.id("particle_soft"))));
 
 
+}
+#[inline]
+pub(crate) fn screenshot_action(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
+    game_state.read().expect("Could not read gameState in screenshot_action").screenshot_requested.store(true, atomic::Ordering::Relaxed);
+}
+#[inline]
+pub(crate) fn toggle_debug_overlay_action(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
+    game_state.read().expect("Could not read gameState in toggle_debug_overlay_action").debug_overlay_visible.fetch_xor(true, atomic::Ordering::Relaxed);
+}
+#[inline]
+pub(crate) fn toggle_post_processing_action(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
+    let mut game_state = game_state.write().expect("Could not write gameState in toggle_post_processing_action");
+    game_state.post_processing_enabled = !game_state.post_processing_enabled;
+    println!("Post processing {}", if game_state.post_processing_enabled { "on" } else { "off" });
+}
+#[inline]
+pub(crate) fn toggle_post_effect_1_action(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
+    toggle_post_effect(0, game_state);
+}
+#[inline]
+pub(crate) fn toggle_post_effect_2_action(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
+    toggle_post_effect(1, game_state);
+}
+#[inline]
+pub(crate) fn toggle_post_effect_3_action(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
+    toggle_post_effect(2, game_state);
+}
+#[inline]
+pub(crate) fn toggle_post_effect_4_action(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
+    toggle_post_effect(3, game_state);
+}
+fn toggle_post_effect(index: usize, game_state: &Arc<RwLock<GameState>>){
+    match game_state.write().expect("Could not write gameState in toggle_post_effect").toggle_post_effect(index) {
+        Some((effect, enabled)) => println!("Post effect {} {}", effect.name(), if enabled { "on" } else { "off" }),
+        None => println!("The post processing chain has no effect number {}, see --post-effects", index + 1),
+    }
 }
 #[inline]
 pub(crate) fn simulate_mouse_wheel_up(game_state: &Arc<RwLock<GameState>>, _model: &Arc<Model>){
@@ -208,12 +284,10 @@ fn mouse_moved_action(x: f32, y: f32, game_state: &Arc<RwLock<GameState>>){
     let screen_center_pos = lock.camera_pos;
     let half_screen_width = lock.window_dimensions_ingame.0;
     let half_screen_height = lock.window_dimensions_ingame.1;
-    let mouse_pos = (lock.cursor_pos_relative.0 as f64, lock.cursor_pos_relative.1 as f64);
-    let w_len = lock.window_dimensions.0 as f32 / 2.0;
-    let h_len = lock.window_dimensions.1 as f32 / 2.0;
 
-    let c_p_r = ( (x - w_len) / w_len , (y - h_len) / h_len);
-    let c_p_ig = (screen_center_pos.0 + mouse_pos.0* half_screen_width, screen_center_pos.1 + mouse_pos.1 * half_screen_height);
+    //relative to the part of the window the world is drawn into, so it is still right with letterboxing
+    let c_p_r = lock.viewport.to_relative((x, y));
+    let c_p_ig = (screen_center_pos.0 + c_p_r.0 as f64 * half_screen_width, screen_center_pos.1 + c_p_r.1 as f64 * half_screen_height);
 
     drop(lock);
     //spend the least amount of time possible in a write lock
@@ -249,7 +323,8 @@ pub fn process_mouse_scroll(delta: MouseScrollDelta, game_state: &Arc<RwLock<Gam
         MouseScrollDelta::LineDelta(_horizontal, vertical) => {
             let mut lock = game_state.write().expect("Could not write to gameState on mouse scroll!");
             println!("{:?} --> {:?}", lock.window_dimensions, lock.window_dimensions_ingame);
-            let change = (vertical as f64 *( 0.1 * lock.window_dimensions_ratio),  vertical as f64 * (0.1 / lock.window_dimensions_ratio));
+            //both sides change by the same factor, so the aspect ratio of the viewport policy is kept
+            let change = (vertical as f64 *( 0.1 * lock.window_dimensions_ratio),  vertical as f64 * 0.1);
             println!("change: {:?}",change);
             lock.window_dimensions_ingame = (lock.window_dimensions_ingame.0 - change.0, lock.window_dimensions_ingame.1 - change.1);
             drop(lock); //could have given ownership to recalculate_mouse_pos instead and thus circumvented another read->write access, but this type of performance optimization should not be necessary
@@ -264,7 +339,7 @@ pub fn process_mouse_scroll(delta: MouseScrollDelta, game_state: &Arc<RwLock<Gam
 }
 
 #[inline]
-pub async fn handle_communication_loop(running: Arc<AtomicBool>, vertex_sender: Sender<Vec<Vertex>>, game_state: Arc<RwLock<GameState>>, model_pointer:  Arc<Model>){
+pub async fn handle_communication_loop(running: Arc<AtomicBool>, vertex_sender: Sender<Frame>, game_state: Arc<RwLock<GameState>>, model_pointer:  Arc<Model>, render_statistics: Arc<RenderStatistics>){
 
    
     let mut loop_helper = LoopHelper::builder()
@@ -272,6 +347,27 @@ pub async fn handle_communication_loop(running: Arc<AtomicBool>, vertex_sender:
     .build_with_target_rate(102.0); // limit to FPS if possible
     let mut current_fps = None;
     let mut delta: f64 = 0.0;
+    //the instances are in world space, so they can be sent again without any changes as long as the model did not change
+    let mut instances: Arc<Vec<Instance>> = Arc::new(Vec::new());
+    let mut instances_version = None;
+    //culled and rebuilt together with the instances, since most of them belong to objects
+    let mut lights: Arc<Vec<LightInstance>> = Arc::new(Vec::new());
+    //how many of the instances are in RenderLayer::Effects or below, the particles are drawn right after those
+    let mut effects_end = 0;
+    //the particles change almost every tick, so they are kept apart from the instances and rebuilt on their own
+    let mut particles: Arc<Vec<Instance>> = Arc::new(Vec::new());
+    let mut particles_version = None;
+    //the area the current instances were culled against, they need to be rebuilt as soon as the visible area is not inside of it anymore
+    let mut culling_area: Option<BoundingBox> = None;
+    let mut culled_for_dimensions = (0.0, 0.0);
+    let mut culling_stats = CullingStats::default();
+    //text and other things that stay at the same spot of the window, in pixels
+    let mut screen_instances: Arc<Vec<Instance>> = Arc::new(Vec::new());
+    //the overlay is only laid out again if its text changed, otherwise the renderer can keep the uploaded instances
+    let mut overlay_shown_text: Option<String> = None;
+    let mut overlay_refreshed = Instant::now();
+    //only replaced when an effect was toggled, the chain is tiny anyway
+    let mut post_effects: Arc<Vec<PostEffect>> = Arc::new(Vec::new());
     while running.load(atomic::Ordering::Relaxed){
         //let delta = now.duration_since(last_executed).unwrap().as_secs_f64();
         let lock = game_state.read().expect("Could not read gameState in communication loop!");
@@ -289,21 +385,66 @@ pub async fn handle_communication_loop(running: Arc<AtomicBool>, vertex_sender:
         });
         let camera_pos = lock.camera_pos;
         let win_dimensions = lock.window_dimensions_ingame;
+        let screenshot = lock.screenshot_requested.swap(false, atomic::Ordering::Relaxed);
+        let overlay_visible = lock.debug_overlay_visible.load(atomic::Ordering::Relaxed);
+        let cursor_pos_ingame = lock.cursor_pos_ingame;
+        let viewport = lock.viewport;
+        let active_post_effects = lock.active_post_effects();
         drop(lock);
+        if *post_effects != active_post_effects {
+            post_effects = Arc::new(active_post_effects);
+        }
         let new_cam_pos = (cam_mov.0 * speed *win_dimensions.0 * delta + camera_pos.0, cam_mov.1 * speed* win_dimensions.1 *  delta + camera_pos.1);
-        let vec1fut = iterate_through_static_objects(&model_pointer, new_cam_pos, win_dimensions);
-        let vec2fut = iterate_through_game_objects(&model_pointer, new_cam_pos, win_dimensions);
-        
-        let (mut ret_vector, additional_vector) = join!(vec1fut, vec2fut);  //this is async, but single threaded, which will result in the computation continuing even if one of the two vectors are currently occupied
-        ret_vector.extend(additional_vector);
-        match vertex_sender.send(ret_vector){
+        let objects_version = model_pointer.objects_version();
+        let visible_area = BoundingBox::around(new_cam_pos, win_dimensions);
+        //zooming in would still be inside of the old area, but then far too much would be drawn, so a change of the visible size always rebuilds
+        let camera_left_culling_area = !matches!(culling_area, Some(area) if area.contains(&visible_area)) || culled_for_dimensions != win_dimensions;
+        if instances_version != Some(objects_version) || camera_left_culling_area {
+            let area = visible_area.expanded(CULLING_MARGIN);
+            let vec1fut = iterate_through_static_objects(&model_pointer, &area);
+            let vec2fut = iterate_through_game_objects(&model_pointer, &area);
+            let vec3fut = iterate_through_labels(&model_pointer);
+            let lights_fut = iterate_through_lights(&model_pointer, &area);
+            
+            let ((mut ret_vector, static_stats), (additional_vector, game_object_stats), label_vector, light_vector) = join!(vec1fut, vec2fut, vec3fut, lights_fut);  //this is async, but single threaded, which will result in the computation continuing even if one of the two vectors are currently occupied
+            ret_vector.extend(additional_vector);
+            ret_vector.extend(label_vector);
+            culling_stats = static_stats + game_object_stats;
+            culling_area = Some(area);
+            culled_for_dimensions = win_dimensions;
+            //sort_by_key is stable, so inside a layer static objects stay in front of game objects and everything keeps the order of the model
+            ret_vector.sort_by_key(|(layer, _)| *layer);
+            effects_end = ret_vector.partition_point(|(layer, _)| *layer <= RenderLayer::Effects);
+            instances = Arc::new(ret_vector.into_iter().map(|(_, instance)| instance).collect());
+            lights = Arc::new(light_vector);
+            instances_version = Some(objects_version);
+        }
+        let current_particles_version = model_pointer.particles_version();
+        if particles_version != Some(current_particles_version) || camera_left_culling_area {
+            particles = Arc::new(model_pointer.particles.read().await.construct_instances(&visible_area.expanded(CULLING_MARGIN)));
+            particles_version = Some(current_particles_version);
+        }
+        //shown right away when switched on, afterwards only refreshed every OVERLAY_REFRESH_INTERVAL
+        if overlay_visible && (overlay_shown_text.is_none() || overlay_refreshed.elapsed() >= OVERLAY_REFRESH_INTERVAL) {
+            overlay_refreshed = Instant::now();
+            let input = OverlayInput { communication_rate: current_fps, instance_count: instances.len(), light_count: lights.len(), post_effects: post_effects.clone(), culling: culling_stats, camera_pos: new_cam_pos, window_dimensions_ingame: win_dimensions, cursor_pos_ingame };
+            let text = overlay_text(&model_pointer, &render_statistics, &input).await;
+            if overlay_shown_text.as_ref() != Some(&text) {
+                screen_instances = Arc::new(overlay_instances(&model_pointer, &text));
+                overlay_shown_text = Some(text);
+            }
+        } else if !overlay_visible && overlay_shown_text.take().is_some() {
+            screen_instances = Arc::new(Vec::new());
+        }
+        let ambient_light = [model_pointer.ambient_light[0], model_pointer.ambient_light[1], model_pointer.ambient_light[2], 1.0];
+        let frame = Frame{ instances: instances.clone(), screen_instances: screen_instances.clone(), camera: CameraUniform::new(new_cam_pos, win_dimensions), particles: particles.clone(), effects_end, lights: lights.clone(), ambient_light, viewport, post_effects: post_effects.clone(), screenshot };
+        match vertex_sender.send(frame){
             Ok(_) => (),
             Err(e) => println!("{:?}", e),
         }
         if let Some(fps) = loop_helper.report_rate() {
+            //shown in the debug overlay (F3)
             current_fps = Some(fps);
-            //println!("FPS: {:?}", current_fps);
-
         }
 
         loop_helper.loop_sleep(); // sleeps to achieve a X FPS rate This is a crate function and not just a regular sleep
@@ -321,15 +462,104 @@ pub async fn handle_communication_loop(running: Arc<AtomicBool>, vertex_sender:
     println!("Gracefully stopping the communications thread");
 }
 
+/**
+ * only objects whose bounding box intersects the culling area get an instance, the rest is only counted
+ */
 #[inline]
-async fn iterate_through_static_objects(model: &Arc<Model>, new_cam_pos:(f64, f64), win_dimensions: (f64,f64)) -> Vec<Vertex>{
+async fn iterate_through_static_objects(model: &Arc<Model>, area: &BoundingBox) -> (Vec<(RenderLayer, Instance)>, CullingStats){
     let lock = model.static_objects.read().await;
-    lock.iter().map(|o| o.construct_vertices(new_cam_pos, win_dimensions)).into_iter().flatten().collect()
+    let ret: Vec<(RenderLayer, Instance)> = lock.iter().filter(|o| o.bounding_box().intersects(area)).map(|o| (o.get_layer(), o.construct_instance())).collect();
+    let stats = CullingStats { drawn: ret.len(), culled: lock.len() - ret.len() };
+    (ret, stats)
 }
 
 
 #[inline]
-async fn iterate_through_game_objects(model: &Arc<Model>, new_cam_pos:(f64, f64), win_dimensions: (f64,f64)) -> Vec<Vertex>{
+async fn iterate_through_game_objects(model: &Arc<Model>, area: &BoundingBox) -> (Vec<(RenderLayer, Instance)>, CullingStats){
     let lock = model.game_objects.read().await;
-    lock.iter().map(|o| o.construct_vertices(new_cam_pos, win_dimensions)).into_iter().flatten().collect()
-}
\ No newline at end of file
+    let ret: Vec<(RenderLayer, Instance)> = lock.iter().filter(|o| o.bounding_box().intersects(area)).map(|o| (o.get_layer(), o.construct_instance())).collect();
+    let stats = CullingStats { drawn: ret.len(), culled: lock.len() - ret.len() };
+    (ret, stats)
+}
+
+
+//labels are few and their size is only known after the layout, so they are not culled
+#[inline]
+async fn iterate_through_labels(model: &Arc<Model>) -> Vec<(RenderLayer, Instance)>{
+    let lock = model.labels.read().await;
+    lock.iter().flat_map(|label| label.construct_instances(&model.font).into_iter().map(move |instance| (label.layer, instance))).collect()
+}
+
+
+/**
+ * the free standing lights of the model and the ones that belong to game objects, culled against the same area as the instances
+ */
+#[inline]
+async fn iterate_through_lights(model: &Arc<Model>, area: &BoundingBox) -> Vec<LightInstance>{
+    let lights = model.lights.read().await;
+    let game_objects = model.game_objects.read().await;
+    let object_lights = game_objects.iter().filter_map(|o| o.get_light());
+    lights.iter().copied().chain(object_lights).filter(|light| light.bounding_box().intersects(area)).map(|light| light.construct_instance()).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{model::game_object::debug_object::DebugObject, drawable_object::{drawable_object::{DrawableObject, PIVOT_BOTTOM_CENTER}, bounding_box::BoundingBox}};
+
+    use super::CULLING_MARGIN;
+
+    //a camera at 0/0 that sees 4x2 world units, the culling area is twice as big in both directions: -4/-2 to 4/2
+    fn culling_area() -> BoundingBox{
+        BoundingBox::around((0.0, 0.0), (2.0, 1.0)).expanded(CULLING_MARGIN)
+    }
+
+    fn is_drawn(object: &DebugObject) -> bool{
+        object.bounding_box().intersects(&culling_area())
+    }
+
+    #[test]
+    fn touching_edges_are_drawn(){
+        assert_eq!(culling_area(), BoundingBox::new((-4.0, -2.0), (4.0, 2.0)));
+        //0.25 is exact in binary, so the edges really touch at 4.0 and -2.0
+        assert!(is_drawn(&DebugObject::new_with_size((4.25, 0.0), 0, 0.25)));
+        assert!(is_drawn(&DebugObject::new_with_size((0.0, -2.25), 0, 0.25)));
+        assert!(!is_drawn(&DebugObject::new_with_size((4.3, 0.0), 0, 0.25)));
+        //a single point shares the corner
+        assert!(BoundingBox::new((4.0, 2.0), (5.0, 3.0)).intersects(&culling_area()));
+    }
+
+    #[test]
+    fn off_screen_objects_are_culled(){
+        //outside of the visible area, but inside of the margin
+        assert!(is_drawn(&DebugObject::new_with_size((3.0, 1.5), 0, 0.2)));
+        for position in [(10.0, 0.0), (-10.0, 0.0), (0.0, 5.0), (0.0, -5.0), (10.0, 10.0)] {
+            assert!(!is_drawn(&DebugObject::new_with_size(position, 0, 0.2)), "{:?}", position);
+        }
+        //bigger than the whole area, every edge is outside
+        assert!(is_drawn(&DebugObject::new_with_size((0.0, 0.0), 0, 50.0)));
+    }
+
+    #[test]
+    fn rotated_objects_keep_all_corners_in_their_box(){
+        //flat and wide above the culling area, standing up it reaches into it
+        let mut object = DebugObject::new_with_size((0.0, -2.5), 0, 1.0).with_size((1.0, 0.05));
+        assert!(!is_drawn(&object));
+        object.rotation = std::f32::consts::FRAC_PI_2;
+        assert!(is_drawn(&object));
+
+        //the box has to cover the rotated rectangle in any rotation, also around a pivot that is not the center
+        let mut object = DebugObject::new_with_size((1.0, 1.0), 0, 1.0).with_size((0.5, 0.25)).with_pivot(PIVOT_BOTTOM_CENTER);
+        for step in 0..16 {
+            object.rotation = step as f32 * std::f32::consts::PI / 8.0;
+            //the farthest corner lies exactly on the circle the box is built from, rounding may put it a tiny bit outside
+            let bounding_box = object.bounding_box().expanded(1e-9);
+            let (sin, cos) = (object.rotation as f64).sin_cos();
+            //the corners relative to the pivot, the object is 1 wide, 0.5 high and stands on its pivot
+            for (x, y) in [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.0), (0.5, 0.0)] {
+                let corner = (1.0 + x * cos - y * sin, 1.0 + x * sin + y * cos);
+                assert!(bounding_box.contains(&BoundingBox::new(corner, corner)), "{:?} is outside of {:?} at step {}", corner, bounding_box, step);
+            }
+        }
+    }
+}
@@ -1,13 +1,15 @@
 use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta, TouchPhase};
 //modifiers: ctrl, shift, alt, represented with binary OR -> 00000111
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ControllerInput{
     MouseInput{  action: MouseInputType},
     KeyboardInput{ key: Option<VirtualKeyCode>, state : ElementState },  
     WindowResized{ dimensions : (u32,u32) },
+    //the window moved to a monitor with another dpi, or the setting of the monitor changed. dimensions is the new physical size
+    ScaleFactorChanged{ scale_factor: f64, dimensions: (u32, u32) },
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MouseInputType{
     Move(f32, f32),
     Click{ button: MouseButton, state: ElementState},
@@ -0,0 +1,78 @@
+use std::{sync::Arc, fmt::Write};
+
+use super::controller::CullingStats;
+use crate::{model::model::Model, view::{render_statistics::RenderStatistics, renderer::Instance, bitmap_font::TextStyle, post_processing::PostEffect}};
+
+
+//in pixels
+const OVERLAY_POSITION: (f32, f32) = (10.0, 8.0);
+const OVERLAY_LINE_HEIGHT: f32 = 20.0;
+const OVERLAY_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+//the text is drawn a second time behind itself, slightly offset, so it stays readable on bright sprites
+const OVERLAY_SHADOW_OFFSET: f32 = 1.5;
+const OVERLAY_SHADOW_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+
+
+/**
+ * everything the overlay shows that is not stored in the model or the render statistics, collected by the communication loop
+ */
+pub(crate) struct OverlayInput{
+    pub communication_rate: Option<f64>,
+    pub instance_count: usize,
+    pub light_count: usize,
+    pub post_effects: Arc<Vec<PostEffect>>,
+    pub culling: CullingStats,
+    pub camera_pos: (f64, f64),
+    //half of the visible width and height, see GameState::window_dimensions_ingame
+    pub window_dimensions_ingame: (f64, f64),
+    pub cursor_pos_ingame: (f64, f64),
+}
+
+
+pub(crate) async fn overlay_text(model: &Arc<Model>, render_statistics: &RenderStatistics, input: &OverlayInput) -> String{
+    let game_objects = model.game_objects.read().await.len();
+    let static_objects = model.static_objects.read().await.len();
+    let labels = model.labels.read().await.len();
+    let particles = model.particles.read().await;
+    let (particle_count, emitter_count) = (particles.particle_count(), particles.emitter_count());
+    drop(particles);
+    let rate = |rate: Option<f64>| rate.map_or("-".to_string(), |rate| format!("{:.1}", rate));
+
+    //writing into a String can't fail
+    let mut text = String::new();
+    writeln!(text, "model: {} ticks/s", rate(model.tick_rate())).unwrap();
+    writeln!(text, "communication: {} loops/s", rate(input.communication_rate)).unwrap();
+    if render_statistics.frames() > 0 {
+        writeln!(text, "render: {:.1} fps ({:.2} ms)", render_statistics.frames_per_second(), render_statistics.frame_time_ms()).unwrap();
+    } else {
+        writeln!(text, "render: -").unwrap();
+    }
+    writeln!(text, "instances: {} ({} vertices)", input.instance_count, input.instance_count * 6).unwrap();
+    writeln!(text, "instance buffer: {} capacity, {} reallocations", render_statistics.instance_buffer_capacity(), render_statistics.instance_buffer_reallocations()).unwrap();
+    writeln!(text, "culling: {} drawn, {} culled", input.culling.drawn, input.culling.culled).unwrap();
+    writeln!(text, "lights: {}", input.light_count).unwrap();
+    writeln!(text, "particles: {} from {} emitters", particle_count, emitter_count).unwrap();
+    if input.post_effects.is_empty() {
+        writeln!(text, "post processing: off").unwrap();
+    } else {
+        let names: Vec<&str> = input.post_effects.iter().map(|effect| effect.name()).collect();
+        writeln!(text, "post processing: {}", names.join(", ")).unwrap();
+    }
+    writeln!(text, "game objects: {}, static objects: {}, labels: {}", game_objects, static_objects, labels).unwrap();
+    writeln!(text, "camera: ({:.2}, {:.2})", input.camera_pos.0, input.camera_pos.1).unwrap();
+    writeln!(text, "zoom: {:.2} x {:.2} visible", input.window_dimensions_ingame.0 * 2.0, input.window_dimensions_ingame.1 * 2.0).unwrap();
+    write!(text, "cursor: ({:.2}, {:.2})", input.cursor_pos_ingame.0, input.cursor_pos_ingame.1).unwrap();
+    text
+}
+
+
+/**
+ * the overlay in screen space, see Frame::screen_instances
+ */
+pub(crate) fn overlay_instances(model: &Arc<Model>, text: &str) -> Vec<Instance>{
+    let shadow_style = TextStyle::new(OVERLAY_LINE_HEIGHT).with_color(OVERLAY_SHADOW_COLOR);
+    let style = TextStyle::new(OVERLAY_LINE_HEIGHT).with_color(OVERLAY_COLOR);
+    let mut instances = model.font.layout(text, (OVERLAY_POSITION.0 + OVERLAY_SHADOW_OFFSET, OVERLAY_POSITION.1 + OVERLAY_SHADOW_OFFSET), &shadow_style);
+    instances.extend(model.font.layout(text, OVERLAY_POSITION, &style));
+    instances
+}
@@ -1,4 +1,8 @@
-use std::sync::{atomic::AtomicU8};
+use std::sync::{atomic::{AtomicU8, AtomicBool}};
+
+use crate::view::{frame::Viewport, post_processing::{PostEffect, TOGGLEABLE_POST_EFFECTS}};
+
+use super::viewport_policy::ViewportPolicy;
 
 
 pub struct GameState{
@@ -6,11 +10,27 @@ pub struct GameState{
     pub camera_pos:  (f64, f64),
     pub cursor_pos_relative: (f32, f32),
     pub cursor_pos_ingame: (f64, f64),
+    //physical pixels
     pub window_dimensions: (u32, u32),
+    //half of the visible width and height in world units
     pub window_dimensions_ingame: (f64, f64),
     pub window_dimensions_ratio: f64,
+    //physical pixels per logical pixel of the monitor the window is on
+    pub scale_factor: f64,
+    pub viewport_policy: ViewportPolicy,
+    //the part of the window the world is drawn into, in physical pixels
+    pub viewport: Viewport,
     pub camera_movement: (CamKeyPressed, CamKeyPressed),
     pub cam_speed: f32,
+    //set by the screenshot keybind, the communication loop resets it and tells the view to capture the next frame
+    pub screenshot_requested: AtomicBool,
+    //toggled with F3, see debug_overlay.rs
+    pub debug_overlay_visible: AtomicBool,
+    //the post processing chain in the order it is applied, every effect can be switched on and off on its own (F5 - F8 for the first four)
+    //The effects from --post-effects start switched on, without any the TOGGLEABLE_POST_EFFECTS are offered, switched off
+    pub post_effects: Vec<(PostEffect, bool)>,
+    //F4 switches the whole chain off without forgetting which effects were on
+    pub post_processing_enabled: bool,
 }
 
 pub enum CamKeyPressed{
@@ -22,10 +42,10 @@ pub enum CamKeyPressed{
 
 
 impl GameState {
-    pub fn new(window_dimensions: (u32, u32)) -> Self{
+    pub fn new(window_dimensions: (u32, u32), viewport_policy: ViewportPolicy, post_effects: Vec<PostEffect>) -> Self{
         const WINDOW_DIMENSIONS_STARTUP: (f64, f64) = (1.6, 0.9);
         //game_state_id is supposed to hold information about what part of the game you're in
-        GameState { 
+        let mut ret = GameState { 
             game_state_id: AtomicU8::new(1),
             camera_pos: (0.0,0.0), cursor_pos_relative: (0.0, 0.0), cursor_pos_ingame: (0.0, 0.0),
             window_dimensions: window_dimensions,
@@ -33,8 +53,68 @@ impl GameState {
             camera_movement: (CamKeyPressed::None, CamKeyPressed::None),
             cam_speed: 1.0f32,
             window_dimensions_ratio : WINDOW_DIMENSIONS_STARTUP.0 / WINDOW_DIMENSIONS_STARTUP.1,
+            scale_factor: 1.0,
+            viewport_policy,
+            viewport: viewport_policy.viewport(window_dimensions),
+            screenshot_requested: AtomicBool::new(false),
+            debug_overlay_visible: AtomicBool::new(false),
+            post_effects: if post_effects.is_empty() {
+                TOGGLEABLE_POST_EFFECTS.iter().map(|effect| (*effect, false)).collect()
+            } else {
+                post_effects.into_iter().map(|effect| (effect, true)).collect()
+            },
+            post_processing_enabled: true,
+
+         };
+        //the startup values are 16:9, the window usually is not
+        ret.fit_view();
+        ret
+    }
+
+    /**
+     * called whenever the size of the window or its scale factor changed, recomputes the visible area according to the viewport policy
+     */
+    pub fn resize_window(&mut self, window_dimensions: (u32, u32), scale_factor: f64){
+        if window_dimensions.0 == 0 || window_dimensions.1 == 0 {
+            return;
+        }
+        let old_size = self.logical_window_size();
+        self.window_dimensions = window_dimensions;
+        self.scale_factor = scale_factor;
+        self.window_dimensions_ingame = self.viewport_policy.resize(self.window_dimensions_ingame, old_size, self.logical_window_size());
+        self.window_dimensions_ratio = self.window_dimensions_ingame.0 / self.window_dimensions_ingame.1;
+        self.viewport = self.viewport_policy.viewport(window_dimensions);
+    }
+
+    /**
+     * brings window_dimensions_ingame back to the aspect ratio the viewport policy wants, e.g. after it was set to a fixed size
+     */
+    pub fn fit_view(&mut self){
+        self.window_dimensions_ingame = self.viewport_policy.fit(self.window_dimensions_ingame, self.logical_window_size());
+        self.window_dimensions_ratio = self.window_dimensions_ingame.0 / self.window_dimensions_ingame.1;
+    }
+
+    pub fn logical_window_size(&self) -> (f64, f64){
+        (self.window_dimensions.0 as f64 / self.scale_factor, self.window_dimensions.1 as f64 / self.scale_factor)
+    }
+
+    /**
+     * the effects the view should apply right now, in order. Empty if post processing is switched off
+     */
+    pub fn active_post_effects(&self) -> Vec<PostEffect>{
+        if !self.post_processing_enabled {
+            return Vec::new();
+        }
+        self.post_effects.iter().filter(|(_, enabled)| *enabled).map(|(effect, _)| *effect).collect()
+    }
 
-         }
+    /**
+     * switches the effect at the given position of the chain on or off, returns it with its new state. None if the chain is shorter than that
+     */
+    pub fn toggle_post_effect(&mut self, index: usize) -> Option<(PostEffect, bool)>{
+        let (effect, enabled) = self.post_effects.get_mut(index)?;
+        *enabled = !*enabled;
+        Some((*effect, *enabled))
     }
 
 
@@ -0,0 +1,325 @@
+use std::{fmt, io, path::{Path, PathBuf}, time::Duration};
+
+use serde::{Deserialize, Deserializer};
+use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta, TouchPhase};
+
+use super::controller_input::{ControllerInput, MouseInputType};
+
+
+/**
+ * The file format of an input script, RON or JSON (picked by the file extension). Every event has either a tick (number of frames the headless runner has taken)
+ * or a time (seconds since the runner started) and one ControllerInput, described by input and the fields that input needs:
+ *
+ *   key             key: "W", state: "pressed" | "released"
+ *   mouse_move      position: (x, y) in physical pixels of the window
+ *   click           button: "left" | "right" | "middle", state: "pressed" | "released"
+ *   scroll          lines: 1.0 (positive is the wheel moving up, like MouseScrollDelta::LineDelta)
+ *   resize          size: (width, height) in physical pixels
+ *   scale_factor    scale_factor: 2.0, size: (width, height)
+ *   left_window / entered_window
+ *
+ * RON:  (events: [ (tick: 10, input: "key", key: "D", state: "pressed"), (time: 1.5, input: "scroll", lines: -2.0) ])
+ * JSON: {"events": [ {"tick": 10, "input": "key", "key": "D", "state": "pressed"}, {"time": 1.5, "input": "scroll", "lines": -2.0} ]}
+ * The kinds are plain strings instead of enums, so the same structure works for both formats (see load). JSON null is not supported, leave the field out instead
+ */
+#[derive(Debug, Deserialize)]
+struct InputScriptFile{
+    events: Vec<InputScriptEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InputScriptEntry{
+    #[serde(default, deserialize_with = "optional")]
+    tick: Option<u64>,
+    #[serde(default, deserialize_with = "optional")]
+    time: Option<f64>,
+    input: String,
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    button: String,
+    #[serde(default, deserialize_with = "optional")]
+    position: Option<(f32, f32)>,
+    #[serde(default, deserialize_with = "optional")]
+    lines: Option<f32>,
+    #[serde(default, deserialize_with = "optional")]
+    size: Option<(u32, u32)>,
+    #[serde(default, deserialize_with = "optional")]
+    scale_factor: Option<f64>,
+}
+
+//optional fields are written without Some(...), in RON as well as in JSON
+fn optional<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<T>, D::Error>{
+    T::deserialize(deserializer).map(Some)
+}
+
+
+#[derive(Debug)]
+pub enum InputScriptError{
+    Read{ path: PathBuf, source: io::Error },
+    Parse{ path: PathBuf, source: ron::Error },
+    //index is the position of the event in the events list, starting at 0
+    InvalidEvent{ index: usize, message: String },
+}
+
+impl fmt::Display for InputScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputScriptError::Read { path, source } => write!(f, "could not read input script {}: {}", path.display(), source),
+            InputScriptError::Parse { path, source } => write!(f, "could not parse input script {}: {}", path.display(), source),
+            InputScriptError::InvalidEvent { index, message } => write!(f, "event {} of the input script: {}", index, message),
+        }
+    }
+}
+
+impl std::error::Error for InputScriptError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InputScriptError::Read { source, .. } => Some(source),
+            InputScriptError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptTime{
+    Tick(u64),
+    Seconds(f64),
+}
+
+impl ScriptTime {
+    fn is_due(&self, ticks: u64, elapsed: Duration) -> bool{
+        match self {
+            ScriptTime::Tick(tick) => ticks >= *tick,
+            ScriptTime::Seconds(seconds) => elapsed.as_secs_f64() >= *seconds,
+        }
+    }
+}
+
+
+/**
+ * A loaded input script, the headless runner feeds its events into the controller channel once they are due, just like the window would
+ */
+pub struct InputScript{
+    //in the order of the file, events that are due at the same time are sent in that order
+    pending: Vec<(ScriptTime, ControllerInput)>,
+}
+
+impl InputScript {
+    pub fn load(path: &Path) -> Result<Self, InputScriptError>{
+        let content = std::fs::read_to_string(path).map_err(|source| InputScriptError::Read { path: path.to_path_buf(), source })?;
+        let is_json = path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("json"));
+        InputScript::parse(&content, is_json, path)
+    }
+
+    //path is only used for the errors
+    fn parse(content: &str, is_json: bool, path: &Path) -> Result<Self, InputScriptError>{
+        let file: InputScriptFile = if is_json {
+            //JSON objects and arrays are valid RON maps and lists, they just can't be read into a struct directly
+            ron::from_str::<ron::Value>(content).and_then(|value| value.into_rust())
+        } else {
+            ron::from_str(content)
+        }.map_err(|source| InputScriptError::Parse { path: path.to_path_buf(), source })?;
+
+        let pending = file.events.into_iter().enumerate()
+            .map(|(index, entry)| entry.to_input().map_err(|message| InputScriptError::InvalidEvent { index, message }))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(InputScript { pending })
+    }
+
+    /**
+     * removes and returns every event that is due after ticks frames and elapsed time
+     */
+    pub fn take_due(&mut self, ticks: u64, elapsed: Duration) -> Vec<ControllerInput>{
+        let mut due = Vec::new();
+        self.pending.retain(|(time, input)| {
+            if time.is_due(ticks, elapsed) {
+                due.push(*input);
+                false
+            } else {
+                true
+            }
+        });
+        due
+    }
+
+    pub fn len(&self) -> usize{
+        self.pending.len()
+    }
+
+    pub fn is_finished(&self) -> bool{
+        self.pending.is_empty()
+    }
+}
+
+
+impl InputScriptEntry {
+    fn to_input(&self) -> Result<(ScriptTime, ControllerInput), String>{
+        let time = match (self.tick, self.time) {
+            (Some(tick), None) => ScriptTime::Tick(tick),
+            (None, Some(seconds)) if seconds >= 0.0 => ScriptTime::Seconds(seconds),
+            (None, Some(seconds)) => return Err(format!("time {} is negative", seconds)),
+            _ => return Err("needs either a tick or a time".to_string()),
+        };
+        let input = match self.input.as_str() {
+            "key" => ControllerInput::KeyboardInput { key: Some(parse_key(&self.key).ok_or_else(|| format!("unknown key \"{}\"", self.key))?), state: self.element_state()? },
+            "mouse_move" => {
+                let (x, y) = self.position.ok_or("mouse_move needs a position")?;
+                ControllerInput::MouseInput { action: MouseInputType::Move(x, y) }
+            },
+            "click" => {
+                let button = match self.button.to_lowercase().as_str() {
+                    "left" => MouseButton::Left,
+                    "right" => MouseButton::Right,
+                    "middle" => MouseButton::Middle,
+                    _ => return Err(format!("unknown mouse button \"{}\", expected left, right or middle", self.button)),
+                };
+                ControllerInput::MouseInput { action: MouseInputType::Click { button, state: self.element_state()? } }
+            },
+            "scroll" => {
+                let lines = self.lines.ok_or("scroll needs lines")?;
+                ControllerInput::MouseInput { action: MouseInputType::Scroll { delta: MouseScrollDelta::LineDelta(0.0, lines), phase: TouchPhase::Moved } }
+            },
+            "resize" => ControllerInput::WindowResized { dimensions: self.size.ok_or("resize needs a size")? },
+            "scale_factor" => ControllerInput::ScaleFactorChanged { scale_factor: self.scale_factor.ok_or("scale_factor needs a scale_factor")?, dimensions: self.size.ok_or("scale_factor needs a size")? },
+            "left_window" => ControllerInput::MouseInput { action: MouseInputType::LeftWindow },
+            "entered_window" => ControllerInput::MouseInput { action: MouseInputType::EnteredWindow },
+            _ => return Err(format!("unknown input \"{}\", expected one of key, mouse_move, click, scroll, resize, scale_factor, left_window, entered_window", self.input)),
+        };
+        Ok((time, input))
+    }
+
+    fn element_state(&self) -> Result<ElementState, String>{
+        match self.state.to_lowercase().as_str() {
+            "pressed" => Ok(ElementState::Pressed),
+            "released" => Ok(ElementState::Released),
+            _ => Err(format!("unknown state \"{}\", expected pressed or released", self.state)),
+        }
+    }
+}
+
+
+/**
+ * the keys an input script can press, named like the VirtualKeyCode variants. Only what the keybinds could possibly use, see button_mapping.rs
+ * The terminal renderer uses this for typed characters as well
+ */
+pub(crate) fn parse_key(name: &str) -> Option<VirtualKeyCode>{
+    use VirtualKeyCode::*;
+    const LETTERS: [VirtualKeyCode; 26] = [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z];
+    const DIGITS: [VirtualKeyCode; 10] = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const FUNCTION_KEYS: [VirtualKeyCode; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+    let upper = name.to_uppercase();
+    let mut chars = upper.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'A'..='Z' => Some(LETTERS[(c as u8 - b'A') as usize]),
+            '0'..='9' => Some(DIGITS[(c as u8 - b'0') as usize]),
+            _ => None,
+        };
+    }
+    if let Some(number) = upper.strip_prefix('F').and_then(|number| number.parse::<usize>().ok()) {
+        return FUNCTION_KEYS.get(number.checked_sub(1)?).copied();
+    }
+    match upper.as_str() {
+        "SPACE" => Some(Space),
+        "ESCAPE" => Some(Escape),
+        "RETURN" | "ENTER" => Some(Return),
+        "TAB" => Some(Tab),
+        "BACK" | "BACKSPACE" => Some(Back),
+        "UP" => Some(Up),
+        "DOWN" => Some(Down),
+        "LEFT" => Some(Left),
+        "RIGHT" => Some(Right),
+        "LCONTROL" => Some(LControl),
+        "RCONTROL" => Some(RControl),
+        "LSHIFT" => Some(LShift),
+        "RSHIFT" => Some(RShift),
+        "LALT" => Some(LAlt),
+        "RALT" => Some(RAlt),
+        "SNAPSHOT" | "PRINTSCREEN" => Some(Snapshot),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta, TouchPhase};
+
+    use crate::controller::controller_input::{ControllerInput, MouseInputType};
+
+    use super::{InputScript, InputScriptError, ScriptTime, parse_key};
+
+    fn parse(content: &str, is_json: bool) -> Result<InputScript, InputScriptError>{
+        InputScript::parse(content, is_json, Path::new(if is_json { "test.json" } else { "test.ron" }))
+    }
+
+    fn expected() -> Vec<(ScriptTime, ControllerInput)>{
+        vec![
+            (ScriptTime::Tick(10), ControllerInput::KeyboardInput { key: Some(VirtualKeyCode::D), state: ElementState::Pressed }),
+            (ScriptTime::Tick(20), ControllerInput::MouseInput { action: MouseInputType::Click { button: MouseButton::Left, state: ElementState::Released } }),
+            (ScriptTime::Seconds(1.5), ControllerInput::MouseInput { action: MouseInputType::Scroll { delta: MouseScrollDelta::LineDelta(0.0, -2.0), phase: TouchPhase::Moved } }),
+            (ScriptTime::Tick(30), ControllerInput::WindowResized { dimensions: (1280, 720) }),
+            (ScriptTime::Tick(30), ControllerInput::MouseInput { action: MouseInputType::Move(10.0, 20.5) }),
+        ]
+    }
+
+    #[test]
+    fn ron_script(){
+        let script = parse(r#"(events: [
+            (tick: 10, input: "key", key: "d", state: "pressed"),
+            (tick: 20, input: "click", button: "Left", state: "released"),
+            (time: 1.5, input: "scroll", lines: -2.0),
+            (tick: 30, input: "resize", size: (1280, 720)),
+            (tick: 30, input: "mouse_move", position: (10.0, 20.5)),
+        ])"#, false).unwrap();
+        assert_eq!(script.pending, expected());
+    }
+
+    #[test]
+    fn json_script(){
+        let mut script = parse(r#"{"events": [
+            {"tick": 10, "input": "key", "key": "D", "state": "pressed"},
+            {"tick": 20, "input": "click", "button": "left", "state": "released"},
+            {"time": 1.5, "input": "scroll", "lines": -2.0},
+            {"tick": 30, "input": "resize", "size": [1280, 720]},
+            {"tick": 30, "input": "mouse_move", "position": [10.0, 20.5]}
+        ]}"#, true).unwrap();
+        assert_eq!(script.pending, expected());
+
+        //events come out once they are due, in the order of the file
+        assert_eq!(script.take_due(20, Duration::from_secs(1)), [expected()[0].1, expected()[1].1]);
+        assert_eq!(script.take_due(30, Duration::from_secs(1)), [expected()[3].1, expected()[4].1]);
+        assert_eq!(script.take_due(30, Duration::from_secs(2)), [expected()[2].1]);
+        assert!(script.is_finished());
+    }
+
+    #[test]
+    fn invalid_scripts_are_rejected(){
+        //null is not supported, the field has to be left out
+        assert!(matches!(parse(r#"{"events": [{"time": null, "tick": 3, "input": "left_window"}]}"#, true), Err(InputScriptError::Parse { .. })));
+        let unknown_key = parse(r#"(events: [(tick: 1, input: "entered_window"), (tick: 2, input: "key", key: "Banana", state: "pressed")])"#, false);
+        assert!(matches!(&unknown_key, Err(InputScriptError::InvalidEvent { index: 1, message }) if message.contains("Banana")), "{:?}", unknown_key.err());
+        assert!(matches!(parse(r#"(events: [(tick: 1, time: 2.0, input: "left_window")])"#, false), Err(InputScriptError::InvalidEvent { index: 0, .. })));
+        assert!(matches!(parse(r#"(events: [(tick: 1, input: "scroll")])"#, false), Err(InputScriptError::InvalidEvent { index: 0, .. })));
+    }
+
+    #[test]
+    fn key_names(){
+        assert_eq!(parse_key("w"), Some(VirtualKeyCode::W));
+        assert_eq!(parse_key("7"), Some(VirtualKeyCode::Key7));
+        assert_eq!(parse_key("F5"), Some(VirtualKeyCode::F5));
+        assert_eq!(parse_key("enter"), Some(VirtualKeyCode::Return));
+        assert_eq!(parse_key("F0"), None);
+        assert_eq!(parse_key("F13"), None);
+        assert_eq!(parse_key("?"), None);
+        assert_eq!(parse_key(""), None);
+    }
+}
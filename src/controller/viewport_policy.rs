@@ -0,0 +1,131 @@
+use crate::view::frame::Viewport;
+
+
+//aspect ratio the game is designed for, this is what Letterbox keeps
+pub const DESIGN_ASPECT: f64 = 16.0 / 9.0;
+
+
+/**
+ * Decides what happens to the visible part of the world (GameState::window_dimensions_ingame) when the window changes its size
+ * Without this, the world would be stretched to whatever the window looks like
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportPolicy{
+    //the visible height stays the same, a wider window shows more of the world to the left and right
+    FixedHeight,
+    //the visible width stays the same, a taller window shows more above and below
+    FixedWidth,
+    //always shows the world with the given aspect ratio (width / height), the rest of the window stays empty
+    Letterbox{ aspect: f64 },
+    //one pixel always covers the same part of the world, a bigger window shows more in every direction
+    Expand,
+}
+
+impl Default for ViewportPolicy {
+    fn default() -> Self {
+        ViewportPolicy::FixedHeight
+    }
+}
+
+impl ViewportPolicy {
+    pub fn parse(value: &str) -> Option<Self>{
+        match value.to_lowercase().as_str() {
+            "fixed-height" => Some(ViewportPolicy::FixedHeight),
+            "fixed-width" => Some(ViewportPolicy::FixedWidth),
+            "letterbox" => Some(ViewportPolicy::Letterbox { aspect: DESIGN_ASPECT }),
+            "expand" => Some(ViewportPolicy::Expand),
+            _ => None,
+        }
+    }
+
+    /**
+     * the new half width and half height in world units after the window was resized. The sizes of the window are in logical pixels,
+     * so a window that only moved to a screen with another scale factor keeps showing the same part of the world
+     */
+    pub fn resize(&self, window_dimensions_ingame: (f64, f64), old_size: (f64, f64), new_size: (f64, f64)) -> (f64, f64){
+        match self {
+            //a window without a size (e.g. minimized) would shrink the world to nothing, and it could never grow back from there
+            ViewportPolicy::Expand if old_size.0 > 0.0 && old_size.1 > 0.0 && new_size.0 > 0.0 && new_size.1 > 0.0 => {
+                (window_dimensions_ingame.0 * new_size.0 / old_size.0, window_dimensions_ingame.1 * new_size.1 / old_size.1)
+            },
+            _ => self.fit(window_dimensions_ingame, new_size),
+        }
+    }
+
+    /**
+     * adjusts the visible area to the aspect ratio the policy wants for a window of the given size, e.g. after zooming to a fixed size
+     */
+    pub fn fit(&self, window_dimensions_ingame: (f64, f64), window_size: (f64, f64)) -> (f64, f64){
+        let (half_width, half_height) = window_dimensions_ingame;
+        let window_aspect = window_size.0 / window_size.1;
+        if !window_aspect.is_finite() || window_aspect <= 0.0 {
+            return window_dimensions_ingame;
+        }
+        match self {
+            ViewportPolicy::FixedHeight | ViewportPolicy::Expand => (half_height * window_aspect, half_height),
+            ViewportPolicy::FixedWidth => (half_width, half_width / window_aspect),
+            ViewportPolicy::Letterbox { aspect } => (half_height * aspect, half_height),
+        }
+    }
+
+    /**
+     * the part of the window (in physical pixels) the world is drawn into. This is the whole window for everything but Letterbox
+     */
+    pub fn viewport(&self, window_dimensions: (u32, u32)) -> Viewport{
+        let (width, height) = (window_dimensions.0 as f32, window_dimensions.1 as f32);
+        match self {
+            ViewportPolicy::Letterbox { aspect } => {
+                let aspect = *aspect as f32;
+                if width / height > aspect {
+                    //bars on the left and right
+                    let bar = (width - height * aspect) / 2.0;
+                    Viewport { x: bar, y: 0.0, width: height * aspect, height }
+                } else {
+                    //bars at the top and bottom
+                    let bar = (height - width / aspect) / 2.0;
+                    Viewport { x: 0.0, y: bar, width, height: width / aspect }
+                }
+            },
+            _ => Viewport::full(window_dimensions),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::view::frame::Viewport;
+
+    use super::{ViewportPolicy, DESIGN_ASPECT};
+
+    #[test]
+    fn resizing_follows_the_policy(){
+        //the window starts at 1600x900 and shows 3.2x1.8 world units, the scale factor is 1, so logical and physical pixels are the same
+        let start = (1.6, 0.9);
+        let letterbox = ViewportPolicy::Letterbox { aspect: DESIGN_ASPECT };
+        let full = |width: f32, height: f32| Viewport { x: 0.0, y: 0.0, width, height };
+        let table = [
+            (ViewportPolicy::FixedHeight, (1000, 1000), (0.9, 0.9), full(1000.0, 1000.0)),
+            (ViewportPolicy::FixedHeight, (2000, 500), (3.6, 0.9), full(2000.0, 500.0)),
+            (ViewportPolicy::FixedWidth, (1000, 1000), (1.6, 1.6), full(1000.0, 1000.0)),
+            (ViewportPolicy::FixedWidth, (1600, 400), (1.6, 0.4), full(1600.0, 400.0)),
+            //bars at the top and bottom
+            (letterbox, (1000, 1000), (1.6, 0.9), Viewport { x: 0.0, y: 218.75, width: 1000.0, height: 562.5 }),
+            //bars on the left and right
+            (letterbox, (2000, 900), (1.6, 0.9), Viewport { x: 200.0, y: 0.0, width: 1600.0, height: 900.0 }),
+            (ViewportPolicy::Expand, (3200, 900), (3.2, 0.9), full(3200.0, 900.0)),
+            (ViewportPolicy::Expand, (800, 450), (0.8, 0.45), full(800.0, 450.0)),
+            //a window without a size keeps showing what it showed before
+            (ViewportPolicy::FixedHeight, (0, 0), start, full(0.0, 0.0)),
+            (ViewportPolicy::FixedWidth, (0, 0), start, full(0.0, 0.0)),
+            (letterbox, (0, 0), start, full(0.0, 0.0)),
+            (ViewportPolicy::Expand, (0, 0), start, full(0.0, 0.0)),
+            (ViewportPolicy::Expand, (0, 900), start, full(0.0, 900.0)),
+        ];
+        for (policy, window, expected_dimensions, expected_viewport) in table {
+            let dimensions = policy.resize(start, (1600.0, 900.0), (window.0 as f64, window.1 as f64));
+            assert!((dimensions.0 - expected_dimensions.0).abs() < 1e-9 && (dimensions.1 - expected_dimensions.1).abs() < 1e-9, "{:?} at {:?}: {:?}", policy, window, dimensions);
+            assert_eq!(policy.viewport(window), expected_viewport, "{:?} at {:?}", policy, window);
+        }
+    }
+}
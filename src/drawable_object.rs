@@ -1,2 +1,7 @@
 pub mod drawable_object;
-pub mod static_object;
\ No newline at end of file
+pub mod static_object;
+pub mod render_layer;
+pub mod animation;
+pub mod text_label;
+pub mod bounding_box;
+pub mod point_light;
\ No newline at end of file
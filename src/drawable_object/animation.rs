@@ -0,0 +1,233 @@
+use std::{sync::Arc, fmt};
+
+
+/**
+ * A texture that contains several frames of an animation, laid out in a grid of equally sized cells.
+ * The frames are numbered row by row, starting at the top left cell. Every frame has its own duration in seconds
+ */
+#[derive(Debug, Clone)]
+pub struct SpriteSheet{
+    columns: u32,
+    rows: u32,
+    frame_durations: Vec<f64>,
+}
+
+impl SpriteSheet {
+    pub fn new(columns: u32, rows: u32, frame_durations: Vec<f64>) -> Result<Self, SpriteSheetError>{
+        if columns == 0 || rows == 0 {
+            return Err(SpriteSheetError::NoCells { columns, rows });
+        }
+        if frame_durations.is_empty() {
+            return Err(SpriteSheetError::NoFrames);
+        }
+        //u64 can't overflow here, two u32 multiplied fit into it
+        if frame_durations.len() as u64 > columns as u64 * rows as u64 {
+            return Err(SpriteSheetError::TooManyFrames { columns, rows, frames: frame_durations.len() });
+        }
+        //Animation::advance skips frames until the elapsed time is used up, that never happens with frames that take no time (or NaN)
+        if let Some((frame, duration)) = frame_durations.iter().enumerate().find(|(_, duration)| duration.is_nan() || **duration <= 0.0) {
+            return Err(SpriteSheetError::InvalidDuration { frame, duration: *duration });
+        }
+        Ok(SpriteSheet { columns, rows, frame_durations })
+    }
+
+    /**
+     * every frame is shown for the same amount of time
+     */
+    pub fn uniform(columns: u32, rows: u32, frame_count: u32, frame_duration: f64) -> Result<Self, SpriteSheetError>{
+        SpriteSheet::new(columns, rows, vec![frame_duration; frame_count as usize])
+    }
+
+    pub fn frame_count(&self) -> usize{
+        self.frame_durations.len()
+    }
+
+    /**
+     * [left, top, right, bottom] of the given frame in texture coordinates, see DrawableObject::uv_rect
+     */
+    pub fn uv_rect(&self, frame: usize) -> [f32; 4]{
+        let column = (frame as u32 % self.columns) as f32;
+        let row = (frame as u32 / self.columns) as f32;
+        let width = 1.0 / self.columns as f32;
+        let height = 1.0 / self.rows as f32;
+        [column * width, row * height, (column + 1.0) * width, (row + 1.0) * height]
+    }
+}
+
+
+#[derive(Debug)]
+pub enum SpriteSheetError{
+    NoCells{ columns: u32, rows: u32 },
+    NoFrames,
+    TooManyFrames{ columns: u32, rows: u32, frames: usize },
+    InvalidDuration{ frame: usize, duration: f64 },
+}
+
+impl fmt::Display for SpriteSheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteSheetError::NoCells { columns, rows } => write!(f, "a sprite sheet needs at least one row and one column, got {}x{}", columns, rows),
+            SpriteSheetError::NoFrames => write!(f, "a sprite sheet needs at least one frame"),
+            SpriteSheetError::TooManyFrames { columns, rows, frames } => write!(f, "a sprite sheet with {}x{} cells can't have {} frames", columns, rows, frames),
+            SpriteSheetError::InvalidDuration { frame, duration } => write!(f, "frame {} of the sprite sheet needs a duration above 0, got {}", frame, duration),
+        }
+    }
+}
+
+impl std::error::Error for SpriteSheetError {}
+
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationMode{
+    //start over after the last frame
+    Loop,
+    //stop at the last frame
+    OneShot,
+    //play forwards, then backwards, then forwards again ...
+    PingPong,
+}
+
+
+/**
+ * The state of an animation for one object. The sprite sheet itself is shared, so many objects can play the same animation without copying it
+ */
+#[derive(Debug, Clone)]
+pub struct Animation{
+    sheet: Arc<SpriteSheet>,
+    mode: AnimationMode,
+    current_frame: usize,
+    //time spent on the current frame
+    elapsed: f64,
+    forwards: bool,
+    finished: bool,
+}
+
+impl Animation {
+    pub fn new(sheet: Arc<SpriteSheet>, mode: AnimationMode) -> Self{
+        Animation { sheet, mode, current_frame: 0, elapsed: 0.0, forwards: true, finished: false }
+    }
+
+    /**
+     * moves the animation forward by delta_time seconds, skipping frames if needed. Returns true if the current frame changed
+     */
+    pub fn advance(&mut self, delta_time: f64) -> bool{
+        if self.finished {
+            return false;
+        }
+        let start_frame = self.current_frame;
+        self.elapsed += delta_time;
+        while !self.finished && self.elapsed >= self.sheet.frame_durations[self.current_frame] {
+            //SpriteSheet::new makes sure every duration is above 0, so this ends
+            self.elapsed -= self.sheet.frame_durations[self.current_frame];
+            self.next_frame();
+        }
+        start_frame != self.current_frame
+    }
+
+    fn next_frame(&mut self){
+        let last_frame = self.sheet.frame_count() - 1;
+        match self.mode {
+            AnimationMode::Loop => self.current_frame = if self.current_frame >= last_frame { 0 } else { self.current_frame + 1 },
+            AnimationMode::OneShot => {
+                if self.current_frame >= last_frame {
+                    self.finished = true;
+                } else {
+                    self.current_frame += 1;
+                }
+            },
+            AnimationMode::PingPong => {
+                if last_frame == 0 {
+                    return;
+                }
+                if self.forwards && self.current_frame >= last_frame {
+                    self.forwards = false;
+                } else if !self.forwards && self.current_frame == 0 {
+                    self.forwards = true;
+                }
+                if self.forwards {
+                    self.current_frame += 1;
+                } else {
+                    self.current_frame -= 1;
+                }
+            },
+        }
+    }
+
+    pub fn uv_rect(&self) -> [f32; 4]{
+        self.sheet.uv_rect(self.current_frame)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{SpriteSheet, SpriteSheetError, Animation, AnimationMode};
+
+    //4 frames of 0.1 seconds each
+    fn animation(mode: AnimationMode) -> Animation{
+        Animation::new(Arc::new(SpriteSheet::uniform(2, 2, 4, 0.1).unwrap()), mode)
+    }
+
+    #[test]
+    fn loop_wraps_around(){
+        let mut animation = animation(AnimationMode::Loop);
+        assert!(!animation.advance(0.05));
+        assert_eq!(animation.current_frame, 0);
+        for expected in [1, 2, 3, 0, 1] {
+            assert!(animation.advance(0.1));
+            assert_eq!(animation.current_frame, expected);
+        }
+        assert!(!animation.finished);
+        assert_eq!(animation.uv_rect(), [0.5, 0.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn one_shot_stops_at_the_last_frame(){
+        let mut animation = animation(AnimationMode::OneShot);
+        assert!(animation.advance(0.35));
+        assert_eq!(animation.current_frame, 3);
+        assert!(!animation.finished);
+        assert!(!animation.advance(0.1));
+        assert!(animation.finished);
+        assert!(!animation.advance(10.0));
+        assert_eq!(animation.current_frame, 3);
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_both_ends(){
+        let mut animation = animation(AnimationMode::PingPong);
+        let mut frames = Vec::new();
+        for _ in 0..8 {
+            animation.advance(0.1);
+            frames.push(animation.current_frame);
+        }
+        assert_eq!(frames, [1, 2, 3, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn big_steps_skip_frames(){
+        let mut animation = animation(AnimationMode::Loop);
+        //two and a half frames, the rest of the time is kept for the next step
+        assert!(animation.advance(0.25));
+        assert_eq!(animation.current_frame, 2);
+        assert!(animation.advance(0.07));
+        assert_eq!(animation.current_frame, 3);
+        //more than a whole round
+        assert!(animation.advance(0.5));
+        assert_eq!(animation.current_frame, 0);
+    }
+
+    #[test]
+    fn invalid_sheets_are_rejected(){
+        assert!(matches!(SpriteSheet::uniform(0, 2, 1, 0.1), Err(SpriteSheetError::NoCells { .. })));
+        assert!(matches!(SpriteSheet::new(2, 2, Vec::new()), Err(SpriteSheetError::NoFrames)));
+        assert!(matches!(SpriteSheet::uniform(2, 2, 5, 0.1), Err(SpriteSheetError::TooManyFrames { frames: 5, .. })));
+        assert!(matches!(SpriteSheet::new(2, 2, vec![0.1, 0.0]), Err(SpriteSheetError::InvalidDuration { frame: 1, .. })));
+        assert!(matches!(SpriteSheet::new(2, 2, vec![f64::NAN]), Err(SpriteSheetError::InvalidDuration { frame: 0, .. })));
+        //columns * rows is above u32::MAX, this must neither overflow nor reject the sheet
+        assert!(SpriteSheet::uniform(u32::MAX, 2, 3, 0.1).is_ok());
+    }
+}
@@ -0,0 +1,38 @@
+/**
+ * An axis aligned rectangle in world space. Like everywhere else in the game, y points down, so min is the top left corner
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox{
+    pub min: (f64, f64),
+    pub max: (f64, f64),
+}
+
+impl BoundingBox {
+    pub fn new(min: (f64, f64), max: (f64, f64)) -> Self{
+        BoundingBox { min, max }
+    }
+
+    /**
+     * half_extents is half of the width and height, just like GameState::window_dimensions_ingame
+     */
+    pub fn around(center: (f64, f64), half_extents: (f64, f64)) -> Self{
+        BoundingBox::new((center.0 - half_extents.0, center.1 - half_extents.1), (center.0 + half_extents.0, center.1 + half_extents.1))
+    }
+
+    /**
+     * grows the box on every side by the given fraction of its width/height
+     */
+    pub fn expanded(&self, fraction: f64) -> Self{
+        let margin = ((self.max.0 - self.min.0) * fraction, (self.max.1 - self.min.1) * fraction);
+        BoundingBox::new((self.min.0 - margin.0, self.min.1 - margin.1), (self.max.0 + margin.0, self.max.1 + margin.1))
+    }
+
+    //touching edges count as intersecting, so objects right at the border of the screen are not culled
+    pub fn intersects(&self, other: &BoundingBox) -> bool{
+        self.min.0 <= other.max.0 && other.min.0 <= self.max.0 && self.min.1 <= other.max.1 && other.min.1 <= self.max.1
+    }
+
+    pub fn contains(&self, other: &BoundingBox) -> bool{
+        self.min.0 <= other.min.0 && self.min.1 <= other.min.1 && other.max.0 <= self.max.0 && other.max.1 <= self.max.1
+    }
+}
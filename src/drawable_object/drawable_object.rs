@@ -1,165 +1,143 @@
-use crate::view::renderer::Vertex;
+use crate::view::renderer::Instance;
+
+use super::{render_layer::RenderLayer, bounding_box::BoundingBox, point_light::PointLight};
+
+//pivots for get_pivot, in the same coordinates as the corners of UNIT_QUAD
+#[allow(dead_code)]
+pub const PIVOT_CENTER: [f32; 2] = [0.5, 0.5];
+#[allow(dead_code)]
+pub const PIVOT_BOTTOM_CENTER: [f32; 2] = [0.5, 1.0];
+#[allow(dead_code)]
+pub const PIVOT_TOP_LEFT: [f32; 2] = [0.0, 0.0];
 
 pub trait DrawableObject:{
 
-/* This is the output for an object that fills out the top right corner of the screen @(0,5/0,5) and uses its whole texture.
-    Vertex { position: [0.0, 0.0], tex_i: 0, tex_coords: [0.0, 1.0], },
-    Vertex { position: [1.0, 1.0],  tex_i: 0, tex_coords: [1.0, 0.0], }, 
-    Vertex { position: [0.0, 1.0],  tex_i: 0, tex_coords: [0.0, 0.0], }, 
-    Vertex { position: [0.0, 0.0], tex_i: 0, tex_coords: [0.0, 1.0], }, 
-    Vertex { position: [1.0, 0.0],  tex_i: 0, tex_coords: [1.0, 1.0], },
-    Vertex { position: [1.0, 1.0],  tex_i: 0, tex_coords: [1.0, 0.0], }, 
+/* This is the output for an unrotated 1x1 object centered at (0.5/0.5) that uses its whole texture.
+    Instance { position: [0.5, 0.5], size: [0.5, 0.5], tex_i: 0, uv_rect: [0.0, 0.0, 1.0, 1.0], tint: [1.0, 1.0, 1.0, 1.0], rotation: 0.0, pivot: [0.5, 0.5], }
+    The unit quad in the vertex buffer is then stretched over the area of the instance in the vertex shader (see UNIT_QUAD)
     */
 
 
 
     /**
      * Default implementation for a somewhat rectangle-shaped object
+     * The instance is in world space, the camera transformation is done in the vertex shader (see CameraUniform)
      */
-
-        /**
-     * Default implementation for a somewhat rectangle-shaped object
-     */
-    #[inline(always)] #[cfg(not(Vulkan))]
-    fn construct_vertices(&self, camera_position: (f64, f64), window_dimensions_ingame: (f64, f64)) -> [Vertex; 6]{
-        let x = (( self.get_position().0 - camera_position.0 ) as f32) /window_dimensions_ingame.0 as f32;
-        let y = (( self.get_position().1 - camera_position.1 ) as f32) /window_dimensions_ingame.1 as f32;
-        let size_x = self.get_size() / (window_dimensions_ingame.0 as f32);
-        let size_y = self.get_size() / (window_dimensions_ingame.1 as f32);
-        let tex_i = self.get_tex_i();
-        //in wgpu: -1 bottom, 1 top, so this needs to be switched around, since in the game logic, a higher y is lower on the screen, just like actual screen coordinates. I don't know why wgpu is doing this, but they want to be an actual math graph.
-        let pre_set: f32 = -1.0;
-        //in wgpu, the vertex faces need to be specified in counter-clockwise order
-        [
-            Vertex{         //TOP RIGHT CORNER
-                position: [x+size_x, pre_set* (y-size_y)],
-                tex_i,
-                tex_coords: self.top_right_coords(),
-            },
-            Vertex{         //TOP LEFT CORNER
-                position: [x-size_x,  pre_set* (y-size_y) ],
-                tex_i,
-                tex_coords: self.top_left_coords(),
-            },
-            Vertex{         //BOTTOM LEFT CORNER
-                position: [x-size_x, pre_set* (y+size_y)],
-                tex_i,
-                tex_coords: self.bottom_left_coords(),
-            },
-
-            Vertex{         //TOP RIGHT CORNER
-                position: [x+size_x, pre_set* (y-size_y)],
-                tex_i,
-                tex_coords: self.top_right_coords(),
-            },            
-            Vertex{         //BOTTOM LEFT CORNER
-                position: [x-size_x, pre_set* (y+size_y)],
-                tex_i,
-                tex_coords: self.bottom_left_coords(),
-            },
-            Vertex{         //BOTTOM RIGHT CORNER
-                 position: [x+size_x, pre_set* (y+size_y)],
-                 tex_i,
-                 tex_coords: self.bottom_right_coords(),
-             },
-
-            
-        ]
-    }
-
-    
-
-
-
-
-    #[inline(always)] #[cfg(Vulkan)]
-    fn construct_vertices(&self, camera_position: (f64, f64), window_dimensions_ingame: (f64, f64)) -> [Vertex; 6]{
-        let x = (( self.get_position().0 - camera_position.0 ) as f32) /window_dimensions_ingame.0 as f32;
-        let y = (( self.get_position().1 - camera_position.1 ) as f32) /window_dimensions_ingame.1 as f32;
-        let size_x = self.get_size() / (window_dimensions_ingame.0 as f32);
-        let size_y = self.get_size() / (window_dimensions_ingame.1 as f32);
-        let tex_i = self.get_tex_i();
-
-        //in wgpu, the vertex faces need to be specified in counter-clockwise order
-        [              
-            Vertex{
-                position: [x+size_x, (y-size_y)],
-                tex_i,
-                tex_coords: self.top_right_coords(),
-            },
-            Vertex{
-                position: [x-size_x,  (y-size_y) ],
-                tex_i,
-                tex_coords: self.top_left_coords(),
-            },
-            Vertex{
-                position: [x-size_x, (y+size_y)],
-                tex_i,
-                tex_coords: self.bottom_left_coords(),
-            },
-            Vertex{
-                position: [x+size_x, (y-size_y)],
-                tex_i,
-                tex_coords: self.top_right_coords(),
-            },
-            Vertex{
-                position: [x-size_x, (y+size_y)],
-                tex_i,
-                tex_coords: self.bottom_right_coords(),
-            },
-            Vertex{
-                position: [x+size_x, y+size_y],
-                tex_i,
-                tex_coords: self.bottom_right_coords(),
-            }
-            
-        ]
+    #[inline(always)]
+    fn construct_instance(&self) -> Instance{
+        let (x, y) = self.get_position();
+        let (width, height) = self.get_size();
+        Instance{
+            position: [x as f32, y as f32],
+            size: [width, height],
+            tex_i: self.get_tex_i(),
+            uv_rect: self.uv_rect(),
+            tint: self.get_tint(),
+            rotation: self.get_rotation(),
+            pivot: self.get_pivot(),
+        }
     }
 
 
 
+    /**
+     * the position of the pivot in world space
+     */
     fn get_position(&self) -> (f64, f64);
-    fn get_size(&self) -> f32;
+    /**
+     * half of the width and half of the height in world space
+     */
+    fn get_size(&self) -> (f32, f32);
     fn get_tex_i(&self) -> u32;
 
-    //Consider making this interior mutable, in order to speed up access to these 
-    fn process_animation(&mut self, delta_time: f64);
+    /**
+     * in radians, clockwise on the screen, the object is rotated around its pivot
+     */
+    #[inline(always)]
+    fn get_rotation(&self) -> f32{
+        0.0
+    }
 
+    /**
+     * the point of the object that is placed at get_position, relative to its size. 0/0 is the top left corner and 1/1 the bottom right one
+     * Buildings use PIVOT_BOTTOM_CENTER, so their position is where they stand on the ground
+     */
+    #[inline(always)]
+    fn get_pivot(&self) -> [f32; 2]{
+        PIVOT_CENTER
+    }
 
     /**
-     * the relative position of the top right vertex if overlayed on top of the image that is to be drawn
+     * the area the object covers in world space, used to skip objects that are not on the screen before their instance is built
+     * Rotated objects get a box that fits them in any rotation, which is a bit too big, but cheap to compute
      */
     #[inline(always)]
-    fn top_right_coords(&self) -> [f32; 2]{
-        //const expressions are evaluated at compile time and thus can be used to explicitly tell the compiler to optimize this. Would most likely happen anyway.
-        const RET: [f32; 2] = [1.0, 0.0];
-        return RET;
+    fn bounding_box(&self) -> BoundingBox{
+        let (x, y) = self.get_position();
+        let (half_width, half_height) = self.get_size();
+        let (width, height) = (half_width as f64 * 2.0, half_height as f64 * 2.0);
+        let pivot = self.get_pivot();
+        let (pivot_x, pivot_y) = (pivot[0] as f64, pivot[1] as f64);
+        if self.get_rotation() == 0.0 {
+            let left = x - pivot_x * width;
+            let top = y - pivot_y * height;
+            return BoundingBox::new((left, top), (left + width, top + height));
+        }
+        //the object rotates around its pivot, so no corner gets further away from it than the farthest one is now
+        let farthest_x = pivot_x.max(1.0 - pivot_x) * width;
+        let farthest_y = pivot_y.max(1.0 - pivot_y) * height;
+        let radius = (farthest_x * farthest_x + farthest_y * farthest_y).sqrt();
+        BoundingBox::around((x, y), (radius, radius))
     }
-    
+
     /**
-     * the relative position of the top left vertex if overlayed on top of the image that is to be drawn
+     * rgba color the texture is multiplied with, e.g. to highlight selected objects or fade them out with a lower alpha. White keeps the texture unchanged
      */
     #[inline(always)]
-    fn top_left_coords(&self) -> [f32; 2]{
-        const RET: [f32; 2] = [0.0, 0.0];
+    fn get_tint(&self) -> [f32; 4]{
+        const RET: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
         return RET;
     }
-    
+
     /**
-     * the relative position of the bottom right vertex if overlayed on top of the image that is to be drawn
+     * a light that moves with the object, in world space. Objects without one return None
      */
     #[inline(always)]
-    fn bottom_right_coords(&self) -> [f32; 2]{
-        const RET: [f32; 2] = [1.0, 1.0];
-        return RET;
+    fn get_light(&self) -> Option<PointLight>{
+        None
+    }
+
+    /**
+     * decides the draw order, objects in a higher layer are drawn on top of the ones in lower layers
+     */
+    #[inline(always)]
+    fn get_layer(&self) -> RenderLayer{
+        RenderLayer::default()
+    }
+
+    /**
+     * called by the model loop every tick, delta_time is in seconds. Returns true if something visible changed (e.g. the frame of the animation), so the instances get rebuilt
+     */
+    //Consider making this interior mutable, in order to speed up access to these
+    fn process_animation(&mut self, delta_time: f64) -> bool;
+
+    /**
+     * false if process_animation never changes anything, the model loop skips these objects
+     */
+    #[inline(always)]
+    fn is_animated(&self) -> bool{
+        false
     }
-    
+
+
     /**
-     * the relative position of the bottom left vertex if overlayed on top of the image that is to be drawn
+     * the part of the texture that is drawn, as [left, top, right, bottom] in texture coordinates (0.0 - 1.0, top left is 0.0/0.0)
+     * Animated objects return the rectangle of their current frame here, see Animation::uv_rect
      */
     #[inline(always)]
-    fn bottom_left_coords(&self) -> [f32; 2]{
-        const RET: [f32; 2] = [0.0, 1.0];
+    fn uv_rect(&self) -> [f32; 4]{
+        //const expressions are evaluated at compile time and thus can be used to explicitly tell the compiler to optimize this. Would most likely happen anyway.
+        const RET: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
         return RET;
     }
 
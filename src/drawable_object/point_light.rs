@@ -0,0 +1,56 @@
+use crate::view::lighting::LightInstance;
+
+use super::bounding_box::BoundingBox;
+
+
+/**
+ * A light that brightens everything in a circle around it, on top of the ambient light of the scene (see Model::ambient_light)
+ * Lights can stand on their own (Model::lights) or move with an object (DrawableObject::get_light)
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight{
+    //center of the light in world space. For lights of objects this is relative to the position of the object
+    pub position: (f64, f64),
+    //in world units, nothing further away gets any light
+    pub radius: f32,
+    //rgb, multiplied with intensity. Values above 1.0 are fine, the light map is not clamped
+    pub color: [f32; 3],
+    pub intensity: f32,
+    //exponent of the fall off towards the edge, 1.0 is linear, higher values keep the light closer to the center
+    pub falloff: f32,
+}
+
+impl PointLight {
+    pub fn new(position: (f64, f64), radius: f32, color: [f32; 3]) -> Self{
+        PointLight { position, radius, color, intensity: 1.0, falloff: 2.0 }
+    }
+    pub fn with_intensity(mut self, intensity: f32) -> Self{
+        self.intensity = intensity;
+        self
+    }
+    pub fn with_falloff(mut self, falloff: f32) -> Self{
+        self.falloff = falloff;
+        self
+    }
+
+    /**
+     * the same light moved by offset, used to place the light of an object relative to its position
+     */
+    pub fn offset_by(mut self, offset: (f64, f64)) -> Self{
+        self.position = (self.position.0 + offset.0, self.position.1 + offset.1);
+        self
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox{
+        BoundingBox::around(self.position, (self.radius as f64, self.radius as f64))
+    }
+
+    pub fn construct_instance(&self) -> LightInstance{
+        LightInstance {
+            position: [self.position.0 as f32, self.position.1 as f32],
+            radius: self.radius,
+            falloff: self.falloff,
+            color: [self.color[0] * self.intensity, self.color[1] * self.intensity, self.color[2] * self.intensity, 1.0],
+        }
+    }
+}
@@ -0,0 +1,20 @@
+
+/**
+ * Objects are drawn layer by layer in the order of this enum (Background first, Ui last). Inside a layer, the order in which they are stored in the model is kept
+ */
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer{
+    Background,
+    Terrain,
+    Buildings,
+    Units,
+    Effects,
+    Ui,
+}
+
+impl Default for RenderLayer {
+    fn default() -> Self {
+        RenderLayer::Units
+    }
+}
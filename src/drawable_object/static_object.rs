@@ -1,5 +1,12 @@
 
 
+use crate::view::renderer::Instance;
+
+use super::{render_layer::RenderLayer, bounding_box::BoundingBox, drawable_object::PIVOT_CENTER};
+
+//static objects do not have a size of their own yet, this is the size of the background tiles in Model::construct_game_objects
+const STATIC_OBJECT_HALF_SIZE: f64 = 0.2;
+
 //these objects are meant to be used as the background or anything static, without any logic attached to it
 pub struct StaticObject{
     pub texture_id: u16,
@@ -7,7 +14,27 @@ pub struct StaticObject{
 }
 
 impl StaticObject {
-    pub fn construct_vertices(&self, _camera_position: (f64, f64), _win_dimensions: (f64, f64)) -> [crate::view::renderer::Vertex; 6]{
-        todo!();
+    /**
+     * the same instance DrawableObject::construct_instance builds for an unrotated, untinted object centered on its position
+     */
+    pub fn construct_instance(&self) -> Instance{
+        let (x, y) = self.position;
+        Instance{
+            position: [x as f32, y as f32],
+            size: [STATIC_OBJECT_HALF_SIZE as f32, STATIC_OBJECT_HALF_SIZE as f32],
+            tex_i: self.texture_id as u32,
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            tint: [1.0, 1.0, 1.0, 1.0],
+            rotation: 0.0,
+            pivot: PIVOT_CENTER,
+        }
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox{
+        BoundingBox::around(self.position, (STATIC_OBJECT_HALF_SIZE, STATIC_OBJECT_HALF_SIZE))
+    }
+
+    pub fn get_layer(&self) -> RenderLayer{
+        RenderLayer::Background
     }
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+use crate::view::{bitmap_font::{BitmapFont, TextStyle, TextAlign}, renderer::Instance};
+
+use super::render_layer::RenderLayer;
+
+
+/**
+ * A piece of text that is placed in the world, e.g. the name above a building. Unlike a DrawableObject it consists of many instances (one per glyph),
+ * so labels are kept in their own collection in the model and laid out with the font when the instances are rebuilt
+ */
+#[derive(Debug, Clone)]
+pub struct TextLabel{
+    pub text: String,
+    //top of the first line, see BitmapFont::layout
+    pub position: (f64, f64),
+    pub style: TextStyle,
+    pub layer: RenderLayer,
+}
+
+impl TextLabel {
+    /**
+     * line_height is in world units
+     */
+    pub fn new(text: &str, position: (f64, f64), line_height: f32) -> Self{
+        TextLabel { text: text.to_string(), position, style: TextStyle::new(line_height), layer: RenderLayer::Ui }
+    }
+    pub fn with_align(mut self, align: TextAlign) -> Self{
+        self.style = self.style.with_align(align);
+        self
+    }
+
+    pub fn construct_instances(&self, font: &BitmapFont) -> Vec<Instance>{
+        font.layout(&self.text, (self.position.0 as f32, self.position.1 as f32), &self.style)
+    }
+}
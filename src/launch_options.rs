@@ -0,0 +1,226 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use wgpu::{Backends, PowerPreference};
+
+use crate::{assets, controller::viewport_policy::ViewportPolicy, view::post_processing::PostEffect};
+
+
+/**
+ * everything that can be configured from the command line. Flags are given as --name, options with a value as --name value
+ */
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions{
+    //do not open a window, see dummy_renderer
+    pub no_gui: bool,
+    //--terminal: do not open a window, draw the world as characters into the terminal instead, see terminal_renderer
+    pub terminal: bool,
+    //if set, the frames are additionally drawn by the software renderer in --no-GUI mode and written to this png file
+    pub software_output: Option<PathBuf>,
+    //which graphics apis wgpu may use, --backend vulkan|gl|metal|dx12|all
+    pub backend: BackendChoice,
+    //--fallback-adapter: only use a software adapter (e.g. llvmpipe / WARP), useful if the gpu driver is broken
+    pub force_fallback_adapter: bool,
+    //--low-power: prefer the integrated gpu over the dedicated one
+    pub low_power: bool,
+    //reload sprites that changed on disk while the game is running. On by default in debug builds, can be changed with --hot-reload / --no-hot-reload
+    pub hot_reload: bool,
+    //what a resized window shows of the world, --viewport fixed-height|fixed-width|letterbox|expand
+    pub viewport_policy: ViewportPolicy,
+    //full screen effects applied to the world in this order, --post-effects vignette,bloom,... or none. Empty if not given, then nothing is applied until F5 - F8 switch something on. Only the wgpu renderer applies them
+    pub post_effects: Vec<PostEffect>,
+    //how many frames per second the headless runner (--no-GUI) takes from the communication loop, --rate 60. 0 takes them as fast as they come
+    pub headless_rate: f64,
+    //--ticks N: the headless runner stops after N frames
+    pub ticks: Option<u64>,
+    //--duration 30s|500ms|2m: the headless runner stops after this long. Plain numbers are seconds
+    pub duration: Option<Duration>,
+    //--script inputs.ron|inputs.json: the headless runner sends the inputs of this file to the controller, see InputScript
+    pub input_script: Option<PathBuf>,
+    //--record frames.bin: the headless runner writes every frame it takes into this file, compare two of them with `dug diff a b`, see frame_recording
+    pub record: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendChoice{
+    Vulkan,
+    Gl,
+    Metal,
+    Dx12,
+    All,
+}
+
+impl Default for BackendChoice {
+    fn default() -> Self {
+        BackendChoice::Vulkan
+    }
+}
+
+impl BackendChoice {
+    fn parse(value: &str) -> Option<Self>{
+        match value.to_lowercase().as_str() {
+            "vulkan" => Some(BackendChoice::Vulkan),
+            "gl" | "opengl" => Some(BackendChoice::Gl),
+            "metal" => Some(BackendChoice::Metal),
+            "dx12" => Some(BackendChoice::Dx12),
+            "all" => Some(BackendChoice::All),
+            _ => None,
+        }
+    }
+
+    pub fn backends(&self) -> Backends{
+        match self {
+            BackendChoice::Vulkan => Backends::VULKAN,
+            BackendChoice::Gl => Backends::GL,
+            BackendChoice::Metal => Backends::METAL,
+            BackendChoice::Dx12 => Backends::DX12,
+            BackendChoice::All => Backends::all(),
+        }
+    }
+}
+
+impl LaunchOptions {
+    pub fn from_args(args: &[String]) -> Self{
+        let backend = match value_of(args, "--backend") {
+            Some(value) => BackendChoice::parse(value).unwrap_or_else(|| {
+                println!("Unknown backend {}, expected one of vulkan, gl, metal, dx12, all. Using the default ({:?})", value, BackendChoice::default());
+                BackendChoice::default()
+            }),
+            None => BackendChoice::default(),
+        };
+        let viewport_policy = match value_of(args, "--viewport") {
+            Some(value) => ViewportPolicy::parse(value).unwrap_or_else(|| {
+                println!("Unknown viewport policy {}, expected one of fixed-height, fixed-width, letterbox, expand. Using the default ({:?})", value, ViewportPolicy::default());
+                ViewportPolicy::default()
+            }),
+            None => ViewportPolicy::default(),
+        };
+        let post_effects = match value_of(args, "--post-effects") {
+            Some(value) => PostEffect::parse_chain(value).unwrap_or_else(|unknown| {
+                println!("Unknown post effect {}, expected a comma separated list of vignette, color-grading, bloom, crt, pixelate, protanopia, deuteranopia, tritanopia or none. Using no post effects", unknown);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        let duration = value_of(args, "--duration").and_then(|value| {
+            let duration = parse_duration(value);
+            if duration.is_none() {
+                println!("Could not read --duration {}, expected something like 30, 30s, 500ms or 2m. Running without a time limit", value);
+            }
+            duration
+        });
+        LaunchOptions {
+            no_gui: has_flag(args, "--no-GUI"),
+            terminal: has_flag(args, "--terminal"),
+            software_output: value_of(args, "--software-output").map(PathBuf::from),
+            backend,
+            force_fallback_adapter: has_flag(args, "--fallback-adapter"),
+            low_power: has_flag(args, "--low-power"),
+            hot_reload: hot_reload(args),
+            viewport_policy,
+            post_effects,
+            headless_rate: number_of(args, "--rate").filter(|rate: &f64| *rate >= 0.0).unwrap_or(DEFAULT_HEADLESS_RATE),
+            ticks: number_of(args, "--ticks"),
+            duration,
+            input_script: value_of(args, "--script").map(PathBuf::from),
+            record: value_of(args, "--record").map(PathBuf::from),
+        }
+    }
+
+    pub fn power_preference(&self) -> PowerPreference{
+        if self.low_power { PowerPreference::LowPower } else { PowerPreference::HighPerformance }
+    }
+}
+
+
+//frames per second of the headless runner, about what a monitor would show
+const DEFAULT_HEADLESS_RATE: f64 = 60.0;
+
+
+fn hot_reload(args: &[String]) -> bool{
+    let requested = has_flag(args, "--hot-reload");
+    if assets::EMBEDDED {
+        if requested {
+            println!("--hot-reload does not work with embedded assets, ignoring it");
+        }
+        return false;
+    }
+    requested || (cfg!(debug_assertions) && !has_flag(args, "--no-hot-reload"))
+}
+
+fn has_flag(args: &[String], name: &str) -> bool{
+    args.iter().any(|arg| arg == name)
+}
+
+fn value_of<'a>(args: &'a [String], name: &str) -> Option<&'a String>{
+    let position = args.iter().position(|arg| arg == name)?;
+    let value = args.get(position + 1);
+    if value.is_none() {
+        println!("{} expects a value, ignoring it", name);
+    }
+    value
+}
+
+fn number_of<T: FromStr>(args: &[String], name: &str) -> Option<T>{
+    let value = value_of(args, name)?;
+    let number = value.parse().ok();
+    if number.is_none() {
+        println!("{} expects a number, got {}, ignoring it", name, value);
+    }
+    number
+}
+
+fn parse_duration(value: &str) -> Option<Duration>{
+    let value = value.trim();
+    let (number, unit_seconds) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60.0)
+    } else {
+        (value, 1.0)
+    };
+    let seconds = number.trim().parse::<f64>().ok()? * unit_seconds;
+    if seconds.is_finite() && seconds >= 0.0 { Some(Duration::from_secs_f64(seconds)) } else { None }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{LaunchOptions, parse_duration};
+
+    fn options(args: &[&str]) -> LaunchOptions{
+        LaunchOptions::from_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn durations_with_and_without_unit(){
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2s"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1m"), Some(Duration::from_secs(60)));
+        //plain numbers are seconds
+        assert_eq!(parse_duration("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("-1"), None);
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("abc"), None);
+        assert_eq!(parse_duration("ms"), None);
+    }
+
+    #[test]
+    fn ticks_and_duration_options(){
+        let parsed = options(&["dug", "--no-GUI", "--ticks", "120", "--duration", "500ms"]);
+        assert_eq!(parsed.ticks, Some(120));
+        assert_eq!(parsed.duration, Some(Duration::from_millis(500)));
+        //invalid values are ignored, the run is not limited then
+        let parsed = options(&["dug", "--ticks", "-1", "--duration", "abc"]);
+        assert_eq!((parsed.ticks, parsed.duration), (None, None));
+        let parsed = options(&["dug", "--ticks", "abc", "--duration", "-1"]);
+        assert_eq!((parsed.ticks, parsed.duration), (None, None));
+        //a missing value as well
+        assert_eq!(options(&["dug", "--duration"]).duration, None);
+        assert_eq!(options(&["dug"]).ticks, None);
+    }
+}
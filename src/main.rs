@@ -1,56 +1,88 @@
-use std::{thread::{ spawn, self, JoinHandle}, sync::{Arc,  atomic::AtomicBool, RwLock}, env};
-use constants::{WINDOW_INIT_X, WINDOW_INIT_Y};
-use controller::{controller_input::ControllerInput, controller::handle_communication_loop, game_state::GameState};
+use std::{thread::{self, JoinHandle}, sync::{Arc,  atomic::AtomicBool, RwLock}, env, path::Path};
+use launch_options::LaunchOptions;
+use constants::{WINDOW_INIT_X, WINDOW_INIT_Y, SPRITE_MANIFEST_PATH, FONT_PATH, FONT_SPRITE_NAME};
+use controller::{controller_input::ControllerInput, controller::handle_communication_loop, game_state::GameState, viewport_policy::ViewportPolicy};
 use flume::Receiver;
 extern crate jpeg_decoder as jpeg;
 use model::{model::{ Model}};
 use tokio::{runtime::{Handle}};
-use view::{renderer::Vertex, dummy_renderer};
+use view::{frame::Frame, dummy_renderer, terminal_renderer, frame_recording, sprite_manifest::Sprites, bitmap_font::BitmapFont, render_statistics::RenderStatistics, post_processing::PostEffect};
 use crate::{view::renderer::wgpu_render, controller::controller::handle_input_loop};
 mod controller;
 mod view;
 mod drawable_object;
 mod constants;
 mod model;
+mod launch_options;
+mod assets;
+mod signals;
 
 fn main(){
     let args: Vec<String> = env::args().collect();
+    //`dug diff a b` compares two frame recordings and exits, nothing else needs to be started for that
+    if args.get(1).map(String::as_str) == Some("diff") {
+        std::process::exit(frame_recording::diff_command(&args[2..]));
+    }
+    let options = LaunchOptions::from_args(&args);
     let rt = tokio::runtime::Runtime::new().unwrap();
     let rt = rt.handle();                               //Cloning a reference to a Handle returns a Copy of an actual Handle
+
+    //the sprites need to be known before the model is constructed, since the model looks up the tex_i of its objects by sprite name
+    let sprites = match Sprites::load(Path::new(SPRITE_MANIFEST_PATH)) {
+        Ok(sprites) => Arc::new(sprites),
+        Err(e) => {
+            eprintln!("Could not load sprites: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let font = match BitmapFont::load(Path::new(FONT_PATH), sprites.id(FONT_SPRITE_NAME), &sprites) {
+        Ok(font) => Arc::new(font),
+        Err(e) => {
+            eprintln!("Could not load font: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    //filled by the renderer, shown in the debug overlay
+    let render_statistics = Arc::new(RenderStatistics::new());
+
     let (threads_vec,
         controller_sender,
         vertex_receiver,
          running)
-          = start_threads(rt.clone());
+          = start_threads(rt.clone(), sprites.clone(), font, render_statistics.clone(), options.viewport_policy, options.post_effects.clone());
 
 
-    if args.contains(&String::from("--no-GUI")){
-        rt.block_on(dummy_renderer::go(threads_vec, running, controller_sender, vertex_receiver, rt.clone()));
+    if options.terminal{
+        rt.block_on(terminal_renderer::go(threads_vec, running, controller_sender, vertex_receiver, sprites, render_statistics, options, rt.clone()));
+    }else if options.no_gui{
+        rt.block_on(dummy_renderer::go(threads_vec, running, controller_sender, vertex_receiver, sprites, render_statistics, options, rt.clone()));
     }else{
         //this will lock the current thread (main) in the event loop. Since this creates a new Window, it should be called from the main thread,
         //otherwise it will lead to cross-platform compatibility problems
-        rt.block_on(wgpu_render(threads_vec, running, controller_sender, vertex_receiver, rt.clone()));
-        //THIS WILL NEVER RETURN. inside wgpu_render there will be an exit called which subsequently ends the whole application (only after waiting for the threads to be joined)
+        rt.block_on(wgpu_render(threads_vec, running, controller_sender, vertex_receiver, sprites, render_statistics, options, rt.clone()));
+        //THIS WILL NEVER RETURN, unless no window could be opened, in that case it falls back to the dummy renderer. Otherwise inside wgpu_render there will be an exit called which subsequently ends the whole application (only after waiting for the threads to be joined)
     }
 
 }
 
 
-fn start_threads(rt: Handle)-> (Vec<JoinHandle<()>>, flume::Sender<ControllerInput>, Receiver<Vec<Vertex>>, Arc<AtomicBool>){
+fn start_threads(rt: Handle, sprites: Arc<Sprites>, font: Arc<BitmapFont>, render_statistics: Arc<RenderStatistics>, viewport_policy: ViewportPolicy, post_effects: Vec<PostEffect>)-> (Vec<JoinHandle<()>>, flume::Sender<ControllerInput>, Receiver<Frame>, Arc<AtomicBool>){
 
     let running = Arc::new(AtomicBool::new(true));
 
 
-    let game_state_arc = create_game_structs();
+    let game_state_arc = create_game_structs(viewport_policy, post_effects);
 
     let thread_running = running.clone();
 
 
-    let model = Arc::new(Model::new());
+    let model = Arc::new(Model::new(sprites, font));
     let thread_mod = model.clone();
-    let model_thread = spawn(move ||{
+    //the names show up in panic messages and when the threads are joined
+    let model_thread = thread::Builder::new().name("model".to_string()).spawn(move ||{
         thread_mod.model_loop(thread_running);
-    });
+    }).expect("Could not spawn the model thread");
 
 
 
@@ -60,9 +92,9 @@ fn start_threads(rt: Handle)-> (Vec<JoinHandle<()>>, flume::Sender<ControllerInp
     let thread_game_state = game_state_arc.clone();
     let (sender, receiver) = flume::unbounded::<ControllerInput>();
 
-    let controller_thread = thread::spawn(move ||{
+    let controller_thread = thread::Builder::new().name("controller".to_string()).spawn(move ||{
         handle_input_loop(thread_running, receiver, thread_game_state, thread_mod);
-    });
+    }).expect("Could not spawn the controller thread");
 
 
 
@@ -74,9 +106,9 @@ fn start_threads(rt: Handle)-> (Vec<JoinHandle<()>>, flume::Sender<ControllerInp
     //let (wakeup_sender, wakeup_receiver) = flume::bounded(1);             //if decided to wake up the controller communication thread instead of letting it run all the time
     let thread_rt = rt.clone();
     let (vertex_sender, vertex_receiver) = flume::bounded(1);
-    let controller_communication_thread = thread::spawn(move ||{
-        thread_rt.block_on(handle_communication_loop(thread_running, vertex_sender, thread_game_state, thread_mod));
-    });
+    let controller_communication_thread = thread::Builder::new().name("communication".to_string()).spawn(move ||{
+        thread_rt.block_on(handle_communication_loop(thread_running, vertex_sender, thread_game_state, thread_mod, render_statistics));
+    }).expect("Could not spawn the communication thread");
 
 
     return (vec![model_thread, controller_thread, controller_communication_thread], sender, vertex_receiver, running );
@@ -88,10 +120,10 @@ fn start_threads(rt: Handle)-> (Vec<JoinHandle<()>>, flume::Sender<ControllerInp
  * if performance is ever going to be as critical as possible, it might be reasonable to un-objectify the model, change it to a loop type function only and share the pointers
  * to the different data field directly to the other threads, just like it's done with game_state 
  */
-fn create_game_structs() -> Arc<RwLock<GameState>>{
+fn create_game_structs(viewport_policy: ViewportPolicy, post_effects: Vec<PostEffect>) -> Arc<RwLock<GameState>>{
 
 
-    let game_state_arc = Arc::new(RwLock::new(GameState::new((WINDOW_INIT_X, WINDOW_INIT_Y))));
+    let game_state_arc = Arc::new(RwLock::new(GameState::new((WINDOW_INIT_X, WINDOW_INIT_Y), viewport_policy, post_effects)));
 
     return game_state_arc;
 }
\ No newline at end of file
@@ -1,2 +1,3 @@
 pub mod model;
-pub(crate) mod game_object;
\ No newline at end of file
+pub(crate) mod game_object;
+pub mod particles;
\ No newline at end of file
@@ -1,29 +1,64 @@
 
-use crate::{drawable_object::drawable_object::DrawableObject};
+use crate::{drawable_object::{drawable_object::{DrawableObject, PIVOT_CENTER, PIVOT_BOTTOM_CENTER}, render_layer::RenderLayer, animation::Animation, point_light::PointLight}, view::sprite_manifest::Sprites};
 
 use super::{GameObject, LogicObject};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct DebugObject{
     pub(crate) position: (f64, f64),
     tex_i: u32,
-    pub size: f32,
+    //half width and half height
+    pub size: (f32, f32),
+    pub rotation: f32,
+    pub pivot: [f32; 2],
+    pub layer: RenderLayer,
+    //if this is None, the whole texture is drawn
+    pub animation: Option<Animation>,
+    pub tint: [f32; 4],
+    //the position of the light is relative to the position of the object
+    pub light: Option<PointLight>,
 }
 
 impl DebugObject{
     #[allow(dead_code)]
     pub fn new(position: (f64, f64), _tex_i : u32) -> Self{
-        DebugObject { position, tex_i: 0, size: 0.4 }
+        DebugObject { position, tex_i: 0, size: (0.4, 0.4), rotation: 0.0, pivot: PIVOT_CENTER, layer: RenderLayer::default(), animation: None, tint: [1.0, 1.0, 1.0, 1.0], light: None }
     }    
-    #[allow(dead_code)]
     pub fn new_with_size(position: (f64, f64), tex_i : u32, size: f32) -> Self{
-        DebugObject { position, tex_i, size }
+        DebugObject { position, tex_i, size: (size, size), rotation: 0.0, pivot: PIVOT_CENTER, layer: RenderLayer::default(), animation: None, tint: [1.0, 1.0, 1.0, 1.0], light: None }
+    }
+    /**
+     * a building standing at position, it is half_height high (times two) and as wide as the aspect ratio of its sprite demands
+     */
+    pub fn new_building(position: (f64, f64), tex_i: u32, half_height: f32, sprites: &Sprites) -> Self{
+        DebugObject::new_with_size(position, tex_i, half_height)
+            .with_size((half_height * sprites.aspect_ratio(tex_i), half_height))
+            .with_pivot(PIVOT_BOTTOM_CENTER)
+            .with_layer(RenderLayer::Buildings)
+    }
+    pub fn with_size(mut self, size: (f32, f32)) -> Self{
+        self.size = size;
+        self
+    }
+    pub fn with_pivot(mut self, pivot: [f32; 2]) -> Self{
+        self.pivot = pivot;
+        self
+    }
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self{
+        self.layer = layer;
+        self
+    }
+    pub fn with_animation(mut self, animation: Animation) -> Self{
+        self.animation = Some(animation);
+        self
+    }
+    pub fn with_light(mut self, light: PointLight) -> Self{
+        self.light = Some(light);
+        self
     }
     #[allow(dead_code)]
     fn copy(&self)-> Box<dyn DrawableObject> {
-        Box::new(DebugObject{
-            ..(*self)
-        })
+        Box::new(self.clone())
     }
 }
 
@@ -44,13 +79,13 @@ unsafe impl Sync for DebugObject{
 
 impl DrawableObject for DebugObject {
     //this can be overridden if needed, to go for custom behavior. 
-    //fn construct_vertices(&self, camera_position: (f64, f64), window_dimensions_ingame: (f64,f64)) -> [crate::view::renderer::Vertex; 6] 
+    //fn construct_instance(&self) -> crate::view::renderer::Instance 
     #[inline(always)]
     fn get_position(&self) -> (f64, f64) {
         self.position
     }
     #[inline(always)]
-    fn get_size(&self) -> f32 {
+    fn get_size(&self) -> (f32, f32) {
         self.size
     }
     #[inline(always)]
@@ -58,8 +93,42 @@ impl DrawableObject for DebugObject {
         self.tex_i
     }
     #[inline(always)]
-    fn process_animation(&mut self, delta_time: f64) {
-        println!("If I had an animation, this would cycle to the next image!, dt: {}", delta_time);
+    fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+    #[inline(always)]
+    fn get_pivot(&self) -> [f32; 2] {
+        self.pivot
+    }
+    #[inline(always)]
+    fn get_tint(&self) -> [f32; 4] {
+        self.tint
+    }
+    #[inline(always)]
+    fn get_light(&self) -> Option<PointLight> {
+        self.light.map(|light| light.offset_by(self.position))
+    }
+    #[inline(always)]
+    fn get_layer(&self) -> RenderLayer {
+        self.layer
+    }
+    #[inline(always)]
+    fn process_animation(&mut self, delta_time: f64) -> bool {
+        match &mut self.animation {
+            Some(animation) => animation.advance(delta_time),
+            None => false,
+        }
+    }
+    #[inline(always)]
+    fn is_animated(&self) -> bool {
+        self.animation.is_some()
+    }
+    #[inline(always)]
+    fn uv_rect(&self) -> [f32; 4] {
+        match &self.animation {
+            Some(animation) => animation.uv_rect(),
+            None => [0.0, 0.0, 1.0, 1.0],
+        }
     }
 
 
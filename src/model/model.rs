@@ -1,12 +1,17 @@
-use std::{sync::{atomic::{AtomicBool, self, AtomicU8}, Arc}, time::Duration, thread::sleep};
+use std::{sync::{atomic::{AtomicBool, self, AtomicU8, AtomicU64}, Arc}};
 
 use futures::executor;
 use rand::{thread_rng};
+use spin_sleep::LoopHelper;
 use tokio::sync::RwLock as AsyncRwLock;
 use tokio::join;
-use crate::{ model::game_object::{ GameObject}, drawable_object::static_object::{StaticObject}};
+use crate::{ model::game_object::{ GameObject}, drawable_object::{static_object::StaticObject, render_layer::RenderLayer, text_label::TextLabel, point_light::PointLight, animation::{SpriteSheet, Animation, AnimationMode}}, constants::AMBIENT_LIGHT, view::{sprite_manifest::Sprites, bitmap_font::{BitmapFont, TextAlign}}};
 
-use super::game_object::debug_object::DebugObject;
+use super::{game_object::debug_object::DebugObject, particles::{ParticleSystem, ParticleEmitter, EmitterSettings}};
+
+
+//how often per second the model loop runs, this is also the rate at which animations are advanced
+const MODEL_TICK_RATE: f64 = 60.0;
 
 
 pub struct Model{
@@ -18,7 +23,25 @@ pub struct Model{
     //like arc. data that is accessed by the model in the loop and only be the model itself should probably be moved into the loop itself
     pub game_objects: Arc<AsyncRwLock<Vec<Box<dyn GameObject + Send + Sync>>>>,
     pub static_objects: Arc<AsyncRwLock<Vec<StaticObject>>>,
+    //text in the world, these count as objects as well, so mark_objects_changed needs to be called after changing them
+    pub labels: Arc<AsyncRwLock<Vec<TextLabel>>>,
+    //lights that do not belong to an object (torches on the walls, lava, ...). Objects bring their own, see DrawableObject::get_light. Also needs mark_objects_changed
+    pub lights: Arc<AsyncRwLock<Vec<PointLight>>>,
+    //rgb brightness of the world where no light reaches
+    pub ambient_light: [f32; 3],
+    //dust, sparks, smoke ... these are not game objects, see ParticleSystem
+    pub particles: Arc<AsyncRwLock<ParticleSystem>>,
+    //is increased every tick in which particles moved, appeared or disappeared, like objects_version
+    particles_version: AtomicU64,
     static_elements_per_row: AtomicU8,
+    //is increased every time game_objects or static_objects change, so the vertices only need to be rebuilt if this differs from the last time
+    objects_version: AtomicU64,
+    //ticks per second as f64 bits, measured over the last second. 0 until the first measurement is done
+    tick_rate: AtomicU64,
+    //name -> tex_i lookup for the sprites defined in the sprite manifest
+    pub sprites: Arc<Sprites>,
+    //used to turn the labels into instances
+    pub font: Arc<BitmapFont>,
    
     
 }
@@ -28,11 +51,20 @@ pub struct Model{
 
 impl Model {
 
-    pub fn new() -> Self{
+    pub fn new(sprites: Arc<Sprites>, font: Arc<BitmapFont>) -> Self{
         Model{
             game_objects: Arc::new(AsyncRwLock::new(Vec::new())),
             static_objects: Arc::new(AsyncRwLock::new(Vec::new())),
+            labels: Arc::new(AsyncRwLock::new(Vec::new())),
+            lights: Arc::new(AsyncRwLock::new(Vec::new())),
+            ambient_light: AMBIENT_LIGHT,
+            particles: Arc::new(AsyncRwLock::new(ParticleSystem::new())),
+            particles_version: AtomicU64::new(0),
             static_elements_per_row: AtomicU8::new(0),
+            objects_version: AtomicU64::new(0),
+            tick_rate: AtomicU64::new(0),
+            sprites,
+            font,
         }
     }
 
@@ -43,23 +75,101 @@ impl Model {
         executor::block_on(self.construct_game_logic());
     
     
+        let mut loop_helper = LoopHelper::builder()
+        .report_interval_s(1.0)
+        .build_with_target_rate(MODEL_TICK_RATE);
+        let mut delta: f64 = 0.0;
+        let mut animated = AnimatedObjects::default();
         while thread_running.load(atomic::Ordering::Relaxed){
 
-            println!("Idling! Later I'll be doing stuff for the game!");
-            
-            
-            sleep(Duration::from_millis(10000));
+            self.process_animations(delta, &mut animated);
+            self.process_particles(delta);
+            if let Some(rate) = loop_helper.report_rate() {
+                self.tick_rate.store(rate.to_bits(), atomic::Ordering::Relaxed);
+            }
+
+            loop_helper.loop_sleep();
+            delta = loop_helper.loop_start_s();
         }
         println!("Oh no! I'm getting terminated! Brhsshh! That's the end of the model_loop!");
     }
 
 
+    /**
+     * advances the animations of all animated game objects, the instances only get rebuilt if at least one of them actually changed its frame
+     * Most objects are not animated, so the write lock is only taken if there is at least one animation, and only those objects are visited
+     */
+    fn process_animations(&self, delta_time: f64, animated: &mut AnimatedObjects){
+        let objects_version = self.objects_version();
+        if animated.objects_version != Some(objects_version) {
+            let lock = self.game_objects.blocking_read();
+            animated.indices = lock.iter().enumerate().filter(|(_, game_object)| game_object.is_animated()).map(|(i, _)| i).collect();
+            animated.objects_version = Some(objects_version);
+        }
+        if animated.indices.is_empty() {
+            return;
+        }
+        let mut lock = self.game_objects.blocking_write();
+        let mut changed = false;
+        for &i in animated.indices.iter() {
+            if let Some(game_object) = lock.get_mut(i) {
+                changed |= game_object.process_animation(delta_time);
+            }
+        }
+        drop(lock);
+        if changed {
+            //the objects themselves did not change, so the indices stay valid. Unless someone else changed something in the meantime, then they are collected again
+            let previous = self.objects_version.fetch_add(1, atomic::Ordering::SeqCst);
+            if animated.objects_version == Some(previous) {
+                animated.objects_version = Some(previous + 1);
+            }
+        }
+    }
+
+    /**
+     * moves all particles, spawns new ones and removes the dead ones
+     */
+    fn process_particles(&self, delta_time: f64){
+        let changed = self.particles.blocking_write().update(delta_time);
+        if changed {
+            self.particles_version.fetch_add(1, atomic::Ordering::SeqCst);
+        }
+    }
+
+    pub fn particles_version(&self) -> u64{
+        self.particles_version.load(atomic::Ordering::SeqCst)
+    }
+
+    /**
+     * needs to be called after every change to game_objects, static_objects, labels or lights, otherwise the change will not be visible
+     * call this after the write lock has been released, in order to not trigger a rebuild that will only see the old data
+     */
+    pub fn mark_objects_changed(&self){
+        self.objects_version.fetch_add(1, atomic::Ordering::SeqCst);
+    }
+
+    pub fn objects_version(&self) -> u64{
+        self.objects_version.load(atomic::Ordering::SeqCst)
+    }
+
+    /**
+     * how many ticks per second the model loop actually achieved, None if it was not measured yet
+     */
+    pub fn tick_rate(&self) -> Option<f64>{
+        match f64::from_bits(self.tick_rate.load(atomic::Ordering::Relaxed)) {
+            rate if rate > 0.0 => Some(rate),
+            _ => None,
+        }
+    }
+
+
 /**
  * logic for creating the game background and needed objects
  */
     async fn construct_game_logic(&self){   
 
         join!(self.construct_static_objects(), self.construct_game_objects());
+        self.mark_objects_changed();
     
     }
 
@@ -76,22 +186,52 @@ impl Model {
             
         let _rng = thread_rng();
         let mut lock = self.game_objects.write().await;
-        let debug_object = Box::new(DebugObject::new((0.0, 3.0), 0));
+        //the light sits in the middle of the house, which is 0.8 high
+        let debug_object = Box::new(DebugObject::new_building((0.0, 3.0), self.sprites.id("Dwarf_BaseHouse"), 0.4, &self.sprites)
+            .with_light(PointLight::new((0.0, -0.4), 1.6, [1.0, 0.78, 0.5]).with_intensity(1.2)));
         lock.push(debug_object);
+        //the house is 0.8 high and stands at y = 3.0, so this is right above its roof
+        self.labels.write().await.push(TextLabel::new("Dwarf base", (0.0, 2.05), 0.12).with_align(TextAlign::Center));
+        //pixel art version next to it, its sprite uses Nearest filtering so it stays sharp when zooming in
+        lock.push(Box::new(DebugObject::new_building((1.2, 3.0), self.sprites.id("Dwarf_BaseHouse_px9"), 0.4, &self.sprites)));
+        let mut lights = self.lights.write().await;
+        let mut particles = self.particles.write().await;
+        let particle_sprite = self.sprites.id("particle_soft");
+        let flame_sprite = self.sprites.id("torch_flame");
+        let flame_sheet = Arc::new(SpriteSheet::uniform(4, 1, 4, 0.12).expect("the torch flame sheet has 4 frames in 4 cells"));
+        //torches along the tunnel
+        for i in 0..50 {
+            let torch = (i as f64 * 2.4, -0.45);
+            //every torch starts at another point of the animation, so they don't flicker in sync
+            let mut flame = Animation::new(flame_sheet.clone(), AnimationMode::Loop);
+            flame.advance(i as f64 * 0.05);
+            //the flame is in the upper half of the sprite, a bit above its center, it should be where the light is
+            lock.push(Box::new(DebugObject::new_with_size((torch.0, torch.1 + 0.04), flame_sprite, 0.12).with_size((0.06, 0.12)).with_layer(RenderLayer::Effects).with_animation(flame)));
+            lights.push(PointLight::new(torch, 1.1, [1.0, 0.6, 0.25]).with_intensity(1.4));
+            particles.add(ParticleEmitter::new(torch, EmitterSettings::sparks(particle_sprite)));
+        }
+        //smoke from the roof of the base
+        particles.add(ParticleEmitter::new((0.12, 2.3), EmitterSettings::smoke(particle_sprite)));
+        drop(particles);
+        //a lava pool left of the start, red and wide but not very bright
+        lights.push(PointLight::new((-1.2, -0.2), 2.0, [1.0, 0.25, 0.05]).with_falloff(1.5));
+        drop(lights);
+        let cracked_floor = self.sprites.id("background1_cracked_floor");
+        let cracked_no_floor = self.sprites.id("background1_cracked_no_floor");
     
         //let mut lock = self.game_objects.write().await;
         for i in 0..5500{
-            let new_debug_object = DebugObject::new_with_size((i as f64 *(0.4), 0.0), 2, 0.20001);
+            let new_debug_object = DebugObject::new_with_size((i as f64 *(0.4), 0.0), cracked_floor, 0.20001).with_layer(RenderLayer::Terrain);
     
             lock.push(Box::new(new_debug_object));
         }
         for i in 0..5500{
-            let new_debug_object = DebugObject::new_with_size((i as f64 *0.4, -0.4), 3, 0.2);
+            let new_debug_object = DebugObject::new_with_size((i as f64 *0.4, -0.4), cracked_no_floor, 0.2).with_layer(RenderLayer::Terrain);
     
             lock.push(Box::new(new_debug_object));
         }
         for i in 0..5500{
-            let new_debug_object = DebugObject::new_with_size((i as f64 *0.4, -0.8), 3, 0.2);
+            let new_debug_object = DebugObject::new_with_size((i as f64 *0.4, -0.8), cracked_no_floor, 0.2).with_layer(RenderLayer::Terrain);
     
             lock.push(Box::new(new_debug_object));
         }
@@ -113,8 +253,13 @@ impl Model {
 }
 
 
-
-
-
-
-
+/**
+ * which of the game objects have an animation, collected again by the model loop whenever the objects version changed
+ */
+#[derive(Debug, Default)]
+struct AnimatedObjects{
+    //into Model::game_objects
+    indices: Vec<usize>,
+    //the objects version the indices were collected at, None before the first time
+    objects_version: Option<u64>,
+}
@@ -0,0 +1,388 @@
+use rand::{Rng, rngs::ThreadRng};
+
+use crate::{drawable_object::{bounding_box::BoundingBox, drawable_object::PIVOT_CENTER}, view::renderer::Instance};
+
+
+/**
+ * What the particles of an emitter look like and how they move. All lengths are in world units, all times in seconds
+ * Particles are not objects of the model, they only live inside their emitter and are updated all at once, see ParticleSystem
+ */
+#[derive(Debug, Clone)]
+pub struct EmitterSettings{
+    pub tex_i: u32,
+    //particles per second, 0 only spawns the burst
+    pub rate: f64,
+    //spawned at once when the emitter is created
+    pub burst: u32,
+    //every particle lives for a random time in this range
+    pub lifetime: (f64, f64),
+    //starting velocity, every axis gets a random value of up to +- velocity_spread added
+    pub velocity: (f64, f64),
+    pub velocity_spread: (f64, f64),
+    //particles spawn at a random point of up to +- this around the emitter
+    pub position_spread: (f64, f64),
+    //added to the y velocity every second, positive falls down (y points down in the world)
+    pub gravity: f64,
+    //how much of the velocity is kept after one second, 1.0 keeps all of it
+    pub drag: f64,
+    //the color and half size are interpolated linearly from start to end over the lifetime of a particle
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub start_size: f32,
+    pub end_size: f32,
+}
+
+impl EmitterSettings {
+    pub fn new(tex_i: u32) -> Self{
+        EmitterSettings {
+            tex_i,
+            rate: 10.0,
+            burst: 0,
+            lifetime: (1.0, 1.0),
+            velocity: (0.0, 0.0),
+            velocity_spread: (0.0, 0.0),
+            position_spread: (0.0, 0.0),
+            gravity: 0.0,
+            drag: 1.0,
+            start_color: [1.0, 1.0, 1.0, 1.0],
+            end_color: [1.0, 1.0, 1.0, 0.0],
+            start_size: 0.05,
+            end_size: 0.05,
+        }
+    }
+
+    /**
+     * brown dust that puffs up and settles again, for digging
+     */
+    pub fn dust(tex_i: u32) -> Self{
+        EmitterSettings::new(tex_i)
+            .with_rate(0.0, 40)
+            .with_lifetime(0.6, 1.2)
+            .with_velocity((0.0, -0.4), (0.5, 0.3))
+            .with_position_spread((0.15, 0.05))
+            .with_gravity(0.9)
+            .with_drag(0.2)
+            .with_colors([0.55, 0.45, 0.35, 0.8], [0.45, 0.38, 0.3, 0.0])
+            .with_sizes(0.03, 0.08)
+    }
+
+    /**
+     * small, bright and fast, they fall down quickly
+     */
+    pub fn sparks(tex_i: u32) -> Self{
+        EmitterSettings::new(tex_i)
+            .with_rate(12.0, 0)
+            .with_lifetime(0.3, 0.7)
+            .with_velocity((0.0, -0.5), (0.6, 0.4))
+            .with_gravity(2.5)
+            .with_colors([1.0, 0.9, 0.5, 1.0], [1.0, 0.35, 0.05, 0.0])
+            .with_sizes(0.012, 0.006)
+    }
+
+    /**
+     * slow, grows while it rises and fades out
+     */
+    pub fn smoke(tex_i: u32) -> Self{
+        EmitterSettings::new(tex_i)
+            .with_rate(6.0, 0)
+            .with_lifetime(2.0, 3.5)
+            .with_velocity((0.05, -0.25), (0.05, 0.05))
+            .with_position_spread((0.03, 0.0))
+            .with_drag(0.8)
+            .with_colors([0.35, 0.35, 0.35, 0.5], [0.2, 0.2, 0.2, 0.0])
+            .with_sizes(0.04, 0.18)
+    }
+
+    pub fn with_rate(mut self, rate: f64, burst: u32) -> Self{
+        self.rate = rate;
+        self.burst = burst;
+        self
+    }
+    pub fn with_lifetime(mut self, min: f64, max: f64) -> Self{
+        self.lifetime = (min, max);
+        self
+    }
+    pub fn with_velocity(mut self, velocity: (f64, f64), spread: (f64, f64)) -> Self{
+        self.velocity = velocity;
+        self.velocity_spread = spread;
+        self
+    }
+    pub fn with_position_spread(mut self, spread: (f64, f64)) -> Self{
+        self.position_spread = spread;
+        self
+    }
+    pub fn with_gravity(mut self, gravity: f64) -> Self{
+        self.gravity = gravity;
+        self
+    }
+    pub fn with_drag(mut self, drag: f64) -> Self{
+        self.drag = drag;
+        self
+    }
+    pub fn with_colors(mut self, start: [f32; 4], end: [f32; 4]) -> Self{
+        self.start_color = start;
+        self.end_color = end;
+        self
+    }
+    pub fn with_sizes(mut self, start: f32, end: f32) -> Self{
+        self.start_size = start;
+        self.end_size = end;
+        self
+    }
+}
+
+
+#[derive(Debug, Clone, Copy)]
+struct Particle{
+    position: (f64, f64),
+    velocity: (f64, f64),
+    age: f64,
+    lifetime: f64,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter{
+    pub position: (f64, f64),
+    pub settings: EmitterSettings,
+    //seconds until the emitter stops spawning, None spawns forever. The emitter is removed once it stopped and all of its particles are gone
+    remaining: Option<f64>,
+    //fractional particles that were not spawned yet, so low rates still work at high tick rates
+    spawn_accumulator: f64,
+    burst_done: bool,
+    particles: Vec<Particle>,
+}
+
+impl ParticleEmitter {
+    pub fn new(position: (f64, f64), settings: EmitterSettings) -> Self{
+        ParticleEmitter { position, settings, remaining: None, spawn_accumulator: 0.0, burst_done: false, particles: Vec::new() }
+    }
+
+    /**
+     * spawns only the burst of the settings and removes itself as soon as those particles are gone
+     */
+    pub fn one_shot(position: (f64, f64), settings: EmitterSettings) -> Self{
+        ParticleEmitter::new(position, settings).with_duration(0.0)
+    }
+
+    pub fn with_duration(mut self, seconds: f64) -> Self{
+        self.remaining = Some(seconds);
+        self
+    }
+
+    fn is_finished(&self) -> bool{
+        matches!(self.remaining, Some(remaining) if remaining <= 0.0) && self.burst_done && self.particles.is_empty()
+    }
+
+    fn spawn(&mut self, rng: &mut ThreadRng){
+        let settings = &self.settings;
+        let random = |rng: &mut ThreadRng, spread: f64| if spread > 0.0 { rng.gen_range(-spread..=spread) } else { 0.0 };
+        let lifetime = if settings.lifetime.1 > settings.lifetime.0 { rng.gen_range(settings.lifetime.0..=settings.lifetime.1) } else { settings.lifetime.0 };
+        let particle = Particle {
+            position: (self.position.0 + random(rng, settings.position_spread.0), self.position.1 + random(rng, settings.position_spread.1)),
+            velocity: (settings.velocity.0 + random(rng, settings.velocity_spread.0), settings.velocity.1 + random(rng, settings.velocity_spread.1)),
+            age: 0.0,
+            lifetime,
+        };
+        self.particles.push(particle);
+    }
+
+    fn update(&mut self, delta_time: f64, rng: &mut ThreadRng){
+        //move and age the existing ones first, so new particles start exactly at the emitter
+        let drag = self.settings.drag.powf(delta_time);
+        let gravity = self.settings.gravity * delta_time;
+        for particle in self.particles.iter_mut() {
+            particle.age += delta_time;
+            particle.velocity = (particle.velocity.0 * drag, particle.velocity.1 * drag + gravity);
+            particle.position = (particle.position.0 + particle.velocity.0 * delta_time, particle.position.1 + particle.velocity.1 * delta_time);
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        if !self.burst_done {
+            for _ in 0..self.settings.burst {
+                self.spawn(rng);
+            }
+            self.burst_done = true;
+        }
+        let spawning_time = match self.remaining {
+            Some(remaining) => {
+                let spawning_time = remaining.min(delta_time).max(0.0);
+                self.remaining = Some(remaining - delta_time);
+                spawning_time
+            },
+            None => delta_time,
+        };
+        self.spawn_accumulator += self.settings.rate * spawning_time;
+        while self.spawn_accumulator >= 1.0 {
+            self.spawn(rng);
+            self.spawn_accumulator -= 1.0;
+        }
+    }
+
+    fn construct_instances(&self, area: &BoundingBox, instances: &mut Vec<Instance>){
+        let settings = &self.settings;
+        for particle in self.particles.iter() {
+            let t = (particle.age / particle.lifetime).clamp(0.0, 1.0) as f32;
+            let size = settings.start_size + (settings.end_size - settings.start_size) * t;
+            if !BoundingBox::around(particle.position, (size as f64, size as f64)).intersects(area) {
+                continue;
+            }
+            let mut tint = [0.0; 4];
+            for channel in 0..4 {
+                tint[channel] = settings.start_color[channel] + (settings.end_color[channel] - settings.start_color[channel]) * t;
+            }
+            instances.push(Instance {
+                position: [particle.position.0 as f32, particle.position.1 as f32],
+                size: [size, size],
+                tex_i: settings.tex_i,
+                uv_rect: [0.0, 0.0, 1.0, 1.0],
+                tint,
+                rotation: 0.0,
+                pivot: PIVOT_CENTER,
+            });
+        }
+    }
+}
+
+
+/**
+ * All emitters of the model. They are kept out of Model::game_objects, so thousands of particles can be updated in one go without boxing and locking each of them
+ * Particles are drawn in RenderLayer::Effects, after all objects of that layer
+ */
+#[derive(Debug, Default)]
+pub struct ParticleSystem{
+    emitters: Vec<ParticleEmitter>,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self{
+        ParticleSystem::default()
+    }
+
+    pub fn add(&mut self, emitter: ParticleEmitter){
+        self.emitters.push(emitter);
+    }
+
+    /**
+     * advances every emitter and its particles by delta_time seconds. Returns true if there is anything to draw, or the last particles just disappeared
+     */
+    pub fn update(&mut self, delta_time: f64) -> bool{
+        let had_particles = self.particle_count() > 0;
+        let mut rng = rand::thread_rng();
+        for emitter in self.emitters.iter_mut() {
+            emitter.update(delta_time, &mut rng);
+        }
+        self.emitters.retain(|emitter| !emitter.is_finished());
+        had_particles || self.particle_count() > 0
+    }
+
+    pub fn particle_count(&self) -> usize{
+        self.emitters.iter().map(|emitter| emitter.particles.len()).sum()
+    }
+
+    pub fn emitter_count(&self) -> usize{
+        self.emitters.len()
+    }
+
+    /**
+     * only particles inside of area get an instance
+     */
+    pub fn construct_instances(&self, area: &BoundingBox) -> Vec<Instance>{
+        let mut instances = Vec::with_capacity(self.particle_count());
+        for emitter in self.emitters.iter() {
+            emitter.construct_instances(area, &mut instances);
+        }
+        instances
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::drawable_object::bounding_box::BoundingBox;
+
+    use super::{ParticleSystem, ParticleEmitter, EmitterSettings};
+
+    //every step is exact in binary, so the spawn accumulator and the ages add up without rounding
+    const DELTA: f64 = 0.125;
+
+    //without any spread and with a fixed lifetime, nothing is random
+    fn settings(rate: f64, burst: u32, lifetime: f64) -> EmitterSettings{
+        EmitterSettings::new(3).with_rate(rate, burst).with_lifetime(lifetime, lifetime)
+    }
+
+    fn everywhere() -> BoundingBox{
+        BoundingBox::around((0.0, 0.0), (100.0, 100.0))
+    }
+
+    #[test]
+    fn emitters_spawn_at_their_rate(){
+        let mut system = ParticleSystem::new();
+        //1.25 particles per step, the fraction is kept for the next ones
+        system.add(ParticleEmitter::new((0.0, 0.0), settings(10.0, 5, 100.0)));
+        let mut counts = Vec::new();
+        for _ in 0..8 {
+            system.update(DELTA);
+            counts.push(system.particle_count());
+        }
+        assert_eq!(counts, [6, 7, 8, 10, 11, 12, 13, 15]);
+
+        //stops spawning after its duration
+        let mut system = ParticleSystem::new();
+        system.add(ParticleEmitter::new((0.0, 0.0), settings(8.0, 0, 100.0)).with_duration(0.5));
+        for _ in 0..8 {
+            system.update(DELTA);
+        }
+        assert_eq!(system.particle_count(), 4);
+    }
+
+    #[test]
+    fn particles_expire_after_their_lifetime(){
+        let mut system = ParticleSystem::new();
+        //one particle per step, each one lives for four steps
+        system.add(ParticleEmitter::new((0.0, 0.0), settings(8.0, 0, 0.5)));
+        let mut counts = Vec::new();
+        for _ in 0..8 {
+            system.update(DELTA);
+            counts.push(system.particle_count());
+        }
+        assert_eq!(counts, [1, 2, 3, 4, 4, 4, 4, 4]);
+
+        //a one shot emitter disappears together with its last particle
+        let mut system = ParticleSystem::new();
+        system.add(ParticleEmitter::one_shot((0.0, 0.0), settings(0.0, 3, 0.25)));
+        assert!(system.update(DELTA));
+        assert_eq!(system.particle_count(), 3);
+        assert!(system.update(DELTA));
+        //the last update still reports a change, so the particles get removed from the screen
+        assert!(system.update(DELTA));
+        assert_eq!((system.particle_count(), system.emitter_count()), (0, 0));
+        assert!(!system.update(DELTA));
+    }
+
+    #[test]
+    fn color_and_size_follow_the_age(){
+        let mut system = ParticleSystem::new();
+        let settings = settings(0.0, 1, 1.0)
+            .with_velocity((1.0, 0.0), (0.0, 0.0))
+            .with_colors([1.0, 0.0, 0.0, 1.0], [0.0, 0.0, 1.0, 0.0])
+            .with_sizes(0.1, 0.3);
+        system.add(ParticleEmitter::new((2.0, 1.0), settings));
+        system.update(DELTA);
+        let instances = system.construct_instances(&everywhere());
+        assert_eq!(instances.len(), 1);
+        assert_eq!((instances[0].position, instances[0].size, instances[0].tint), ([2.0, 1.0], [0.1, 0.1], [1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(instances[0].tex_i, 3);
+
+        for _ in 0..4 {
+            system.update(DELTA);
+        }
+        //halfway through its life and half a second at 1 unit per second to the right
+        let instance = system.construct_instances(&everywhere())[0];
+        assert_eq!(instance.position, [2.5, 1.0]);
+        assert!((instance.size[0] - 0.2).abs() < 1e-6, "{:?}", instance.size);
+        assert_eq!(instance.tint, [0.5, 0.0, 0.5, 0.5]);
+        //particles outside of the area get no instance
+        assert!(system.construct_instances(&BoundingBox::around((10.0, 10.0), (1.0, 1.0))).is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use tokio::runtime::Handle;
+
+
+/**
+ * resolves once the process is asked to stop, by SIGINT (ctrl+c) or SIGTERM (docker stop, kill). Never resolves if the signals can't be listened to
+ * Once this is awaited, ctrl+c no longer kills the process, so whoever awaits it has to shut down
+ */
+pub(crate) async fn termination_signal(){
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => if result.is_err() { terminate.recv().await; },
+                _ = terminate.recv() => {},
+            }
+            return;
+        }
+    }
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/**
+ * clears the running flag on SIGINT or SIGTERM. The communication loop stops on its next round and drops its sender,
+ * which ends the loop of the headless runner and the terminal frontend, they then shut down the other threads like they do when they stop on their own
+ */
+pub(crate) fn stop_on_signal(rt: &Handle, running: Arc<AtomicBool>){
+    rt.spawn(async move {
+        termination_signal().await;
+        println!("Got a termination signal, shutting down");
+        running.store(false, Ordering::SeqCst);
+    });
+}
@@ -1,4 +1,16 @@
 pub mod renderer;
 mod sprite_loading;
 mod renderer_init;
-pub mod dummy_renderer;
\ No newline at end of file
+pub mod sprite_manifest;
+pub mod dummy_renderer;
+pub mod frame;
+mod growable_buffer;
+pub mod software_renderer;
+mod screenshot;
+pub mod bitmap_font;
+pub mod render_statistics;
+mod texture_watcher;
+pub mod lighting;
+pub mod post_processing;
+pub mod terminal_renderer;
+pub mod frame_recording;
\ No newline at end of file
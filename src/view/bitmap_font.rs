@@ -0,0 +1,392 @@
+use std::{collections::HashMap, fmt, io, path::{Path, PathBuf}};
+
+use crate::assets;
+
+use super::{renderer::Instance, sprite_manifest::Sprites};
+
+
+/**
+ * One character in the atlas. Everything is in pixels of the atlas, just like in the .fnt file
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph{
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    //offset from the pen position (top of the line) to the top left corner of the glyph
+    pub x_offset: i32,
+    pub y_offset: i32,
+    //how far the pen moves after this glyph
+    pub x_advance: i32,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign{
+    Left,
+    Center,
+}
+
+/**
+ * How a string is laid out. All lengths are in the units of the target space, that is world units for text in the world and pixels for text on the screen
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct TextStyle{
+    //distance between two lines, the glyphs are scaled accordingly
+    pub line_height: f32,
+    //lines that would get wider than this are wrapped at the last space, words that do not fit on a line at all are split. None never wraps
+    pub max_width: Option<f32>,
+    //lines are aligned relative to the x coordinate of the position that is passed to layout
+    pub align: TextAlign,
+    pub color: [f32; 4],
+}
+
+impl TextStyle {
+    pub fn new(line_height: f32) -> Self{
+        TextStyle { line_height, max_width: None, align: TextAlign::Left, color: [1.0, 1.0, 1.0, 1.0] }
+    }
+    pub fn with_align(mut self, align: TextAlign) -> Self{
+        self.align = align;
+        self
+    }
+    pub fn with_color(mut self, color: [f32; 4]) -> Self{
+        self.color = color;
+        self
+    }
+}
+
+
+#[derive(Debug)]
+pub enum FontLoadingError{
+    Read{ path: PathBuf, source: io::Error },
+    Parse{ path: PathBuf, line: usize, message: String },
+    MissingCommon{ path: PathBuf },
+    MultiplePages{ path: PathBuf, pages: u32 },
+    AtlasSizeMismatch{ path: PathBuf, expected: (u32, u32), actual: (u32, u32) },
+}
+
+impl fmt::Display for FontLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontLoadingError::Read { path, source } => write!(f, "could not read font {}: {}", path.display(), source),
+            FontLoadingError::Parse { path, line, message } => write!(f, "could not parse font {} in line {}: {}", path.display(), line, message),
+            FontLoadingError::MissingCommon { path } => write!(f, "font {} has no \"common\" line", path.display()),
+            FontLoadingError::MultiplePages { path, pages } => write!(f, "font {} has {} pages, only fonts with a single page are supported", path.display(), pages),
+            FontLoadingError::AtlasSizeMismatch { path, expected, actual } => write!(f, "font {} expects an atlas of {}x{} pixels, but the sprite is {}x{}", path.display(), expected.0, expected.1, actual.0, actual.1),
+        }
+    }
+}
+
+impl std::error::Error for FontLoadingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FontLoadingError::Read { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+
+/**
+ * A font in the text format of AngelCode BMFont. The atlas texture is a normal sprite from the sprite manifest, so glyphs are drawn as instances
+ * with the same pipeline as everything else. Glyphs are white, the color of the text comes from the tint
+ */
+#[derive(Debug, Clone)]
+pub struct BitmapFont{
+    tex_i: u32,
+    line_height: f32,
+    atlas_size: (u32, u32),
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), i32>,
+}
+
+impl BitmapFont {
+
+    /**
+     * atlas_tex_i is the sprite that holds the page of the font, its size has to match the one in the .fnt file
+     */
+    pub fn load(path: &Path, atlas_tex_i: u32, sprites: &Sprites) -> Result<Self, FontLoadingError>{
+        let content = assets::read_to_string(path).map_err(|source| FontLoadingError::Read { path: path.to_path_buf(), source })?;
+        let font = BitmapFont::parse(&content, atlas_tex_i, path)?;
+        let actual = sprites.dimensions(atlas_tex_i);
+        if actual != font.atlas_size {
+            return Err(FontLoadingError::AtlasSizeMismatch { path: path.to_path_buf(), expected: font.atlas_size, actual });
+        }
+        Ok(font)
+    }
+
+    fn parse(content: &str, tex_i: u32, path: &Path) -> Result<Self, FontLoadingError>{
+        let mut common = None;
+        let mut glyphs = HashMap::new();
+        let mut kernings = HashMap::new();
+
+        for (line_index, line) in content.lines().enumerate() {
+            let error = |message: String| FontLoadingError::Parse { path: path.to_path_buf(), line: line_index + 1, message };
+            let (tag, attributes) = match split_line(line) {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+            let number = |key: &str| -> Result<i64, FontLoadingError> {
+                let value = attributes.get(key).ok_or_else(|| error(format!("\"{}\" is missing the attribute {}", tag, key)))?;
+                value.parse::<i64>().map_err(|_| error(format!("{}={} is not a number", key, value)))
+            };
+            match tag {
+                "common" => {
+                    let pages = number("pages")? as u32;
+                    if pages != 1 {
+                        return Err(FontLoadingError::MultiplePages { path: path.to_path_buf(), pages });
+                    }
+                    common = Some((number("lineHeight")? as f32, (number("scaleW")? as u32, number("scaleH")? as u32)));
+                },
+                "char" => {
+                    //BMFont uses id=-1 for the "invalid character" glyph, which has no char to go with it
+                    let id = match char::from_u32(number("id")? as u32) {
+                        Some(id) => id,
+                        None => continue,
+                    };
+                    glyphs.insert(id, Glyph {
+                        x: number("x")? as u32,
+                        y: number("y")? as u32,
+                        width: number("width")? as u32,
+                        height: number("height")? as u32,
+                        x_offset: number("xoffset")? as i32,
+                        y_offset: number("yoffset")? as i32,
+                        x_advance: number("xadvance")? as i32,
+                    });
+                },
+                "kerning" => {
+                    if let (Some(first), Some(second)) = (char::from_u32(number("first")? as u32), char::from_u32(number("second")? as u32)) {
+                        kernings.insert((first, second), number("amount")? as i32);
+                    }
+                },
+                //info, page and the counts are not needed
+                _ => (),
+            }
+        }
+
+        let (line_height, atlas_size) = common.ok_or_else(|| FontLoadingError::MissingCommon { path: path.to_path_buf() })?;
+        Ok(BitmapFont { tex_i, line_height, atlas_size, glyphs, kernings })
+    }
+
+    //characters that are not in the font are drawn as '?'
+    fn glyph(&self, c: char) -> Option<&Glyph>{
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn kerning(&self, first: char, second: char) -> i32{
+        self.kernings.get(&(first, second)).copied().unwrap_or(0)
+    }
+
+    /**
+     * width of a single line without wrapping, in the units of the style
+     */
+    pub fn measure_line(&self, line: &str, style: &TextStyle) -> f32{
+        let scale = style.line_height / self.line_height;
+        let mut width = 0;
+        let mut previous = None;
+        for c in line.chars() {
+            if let Some(previous) = previous {
+                width += self.kerning(previous, c);
+            }
+            width += self.glyph(c).map(|glyph| glyph.x_advance).unwrap_or(0);
+            previous = Some(c);
+        }
+        width as f32 * scale
+    }
+
+    /**
+     * splits the text into the lines that get drawn. Explicit line breaks are kept, the rest is wrapped according to style.max_width
+     */
+    pub fn wrap(&self, text: &str, style: &TextStyle) -> Vec<String>{
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let max_width = match style.max_width {
+                Some(max_width) => max_width,
+                None => {
+                    lines.push(paragraph.to_string());
+                    continue;
+                }
+            };
+            let mut current = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+                if self.measure_line(&candidate, style) <= max_width {
+                    current = candidate;
+                    continue;
+                }
+                if !current.is_empty() {
+                    lines.push(std::mem::take(&mut current));
+                }
+                //the word does not even fit on a line of its own, so it gets split wherever the line is full
+                for c in word.chars() {
+                    current.push(c);
+                    if current.chars().count() > 1 && self.measure_line(&current, style) > max_width {
+                        current.pop();
+                        lines.push(std::mem::replace(&mut current, c.to_string()));
+                    }
+                }
+            }
+            lines.push(current);
+        }
+        lines
+    }
+
+    /**
+     * Creates one instance per visible glyph. position is the top of the first line, its x coordinate is the left edge or the center depending on style.align
+     * Like in the rest of the game, y points down, so this works for world space as well as for screen space
+     */
+    pub fn layout(&self, text: &str, position: (f32, f32), style: &TextStyle) -> Vec<Instance>{
+        let scale = style.line_height / self.line_height;
+        let (atlas_width, atlas_height) = (self.atlas_size.0 as f32, self.atlas_size.1 as f32);
+        let mut instances = Vec::with_capacity(text.len());
+
+        for (line_index, line) in self.wrap(text, style).iter().enumerate() {
+            let line_width = self.measure_line(line, style);
+            let line_start = match style.align {
+                TextAlign::Left => position.0,
+                TextAlign::Center => position.0 - line_width / 2.0,
+            };
+            let line_top = position.1 + line_index as f32 * style.line_height;
+            //the pen is kept in atlas pixels, so kerning and advances add up without rounding errors
+            let mut pen = 0;
+            let mut previous = None;
+            for c in line.chars() {
+                if let Some(previous) = previous {
+                    pen += self.kerning(previous, c);
+                }
+                previous = Some(c);
+                let glyph = match self.glyph(c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                if glyph.width > 0 && glyph.height > 0 {
+                    instances.push(Instance {
+                        position: [line_start + (pen + glyph.x_offset) as f32 * scale, line_top + glyph.y_offset as f32 * scale],
+                        size: [glyph.width as f32 * scale / 2.0, glyph.height as f32 * scale / 2.0],
+                        tex_i: self.tex_i,
+                        uv_rect: [
+                            glyph.x as f32 / atlas_width,
+                            glyph.y as f32 / atlas_height,
+                            (glyph.x + glyph.width) as f32 / atlas_width,
+                            (glyph.y + glyph.height) as f32 / atlas_height,
+                        ],
+                        tint: style.color,
+                        rotation: 0.0,
+                        //the position is the top left corner of the glyph
+                        pivot: [0.0, 0.0],
+                    });
+                }
+                pen += glyph.x_advance;
+            }
+        }
+        instances
+    }
+}
+
+
+/**
+ * "char id=65 x=2 ..." -> ("char", {id: 65, x: 2, ...}). Values in quotes may contain spaces, the quotes are removed
+ */
+fn split_line(line: &str) -> Option<(&str, HashMap<&str, &str>)>{
+    let line = line.trim();
+    let (tag, mut rest) = match line.find(' ') {
+        Some(index) => (&line[..index], line[index..].trim_start()),
+        None if !line.is_empty() => (line, ""),
+        None => return None,
+    };
+    let mut attributes = HashMap::new();
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].trim();
+        let value_start = &rest[equals + 1..];
+        let (value, remaining) = if let Some(quoted) = value_start.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match value_start.find(' ') {
+                Some(end) => (&value_start[..end], &value_start[end..]),
+                None => (value_start, ""),
+            }
+        };
+        attributes.insert(key, value);
+        rest = remaining.trim_start();
+    }
+    Some((tag, attributes))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{BitmapFont, TextStyle, TextAlign};
+
+    //a 10 pixel high font with A, V, ? and space. "AV" is kerned by 2 pixels
+    const FONT: &str = r#"info face="Test" size=10
+common lineHeight=10 base=8 scaleW=32 scaleH=16 pages=1
+page id=0 file="test.png"
+chars count=4
+char id=65 x=0 y=0 width=6 height=8 xoffset=0 yoffset=1 xadvance=6
+char id=86 x=8 y=0 width=6 height=8 xoffset=0 yoffset=1 xadvance=6
+char id=63 x=16 y=0 width=4 height=8 xoffset=1 yoffset=1 xadvance=5
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=3
+kernings count=1
+kerning first=65 second=86 amount=-2
+"#;
+
+    fn font() -> BitmapFont{
+        BitmapFont::parse(FONT, 7, Path::new("test.fnt")).unwrap()
+    }
+
+    #[test]
+    fn kerning_moves_the_following_glyph(){
+        let font = font();
+        let style = TextStyle::new(10.0);
+        assert_eq!(font.measure_line("AV", &style), 10.0);
+        assert_eq!(font.measure_line("VA", &style), 12.0);
+
+        let instances = font.layout("AV", (100.0, 50.0), &style);
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].position, [100.0, 51.0]);
+        assert_eq!(instances[1].position, [104.0, 51.0]);
+        assert_eq!(instances[1].tex_i, 7);
+        assert_eq!(instances[1].uv_rect, [0.25, 0.0, 14.0 / 32.0, 0.5]);
+        //twice the line height scales everything, including the kerning
+        let instances = font.layout("AV", (0.0, 0.0), &TextStyle::new(20.0));
+        assert_eq!(instances[1].position, [8.0, 2.0]);
+        assert_eq!(instances[1].size, [6.0, 8.0]);
+    }
+
+    #[test]
+    fn lines_wrap_at_spaces_and_split_long_words(){
+        let font = font();
+        //"AV AV" is 10 + 3 + 10 pixels wide
+        let style = TextStyle { max_width: Some(25.0), ..TextStyle::new(10.0) };
+        assert_eq!(font.wrap("AV AV AV", &style), ["AV AV", "AV"]);
+        let style = TextStyle { max_width: Some(15.0), ..TextStyle::new(10.0) };
+        assert_eq!(font.wrap("AV AV AV", &style), ["AV", "AV", "AV"]);
+        //a word that does not fit on a line of its own is split where the line is full
+        assert_eq!(font.wrap("AAAA", &style), ["AA", "AA"]);
+        //without a maximum width nothing is wrapped
+        assert_eq!(font.wrap("AV AV AV", &TextStyle::new(10.0)), ["AV AV AV"]);
+    }
+
+    #[test]
+    fn newlines_start_a_new_line(){
+        let font = font();
+        let style = TextStyle::new(10.0).with_align(TextAlign::Center);
+        assert_eq!(font.wrap("AV\n\nA", &style), ["AV", "", "A"]);
+
+        let instances = font.layout("AV\nA", (0.0, 0.0), &style);
+        assert_eq!(instances.len(), 3);
+        //every line is centered on its own, the second one is a line height further down
+        assert_eq!(instances[0].position, [-5.0, 1.0]);
+        assert_eq!(instances[2].position, [-3.0, 11.0]);
+        //characters that are not in the font are drawn as '?', spaces only move the pen
+        let instances = font.layout("A x", (0.0, 0.0), &TextStyle::new(10.0));
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[1].position, [10.0, 1.0]);
+    }
+}
@@ -1,15 +1,234 @@
-use std::{thread::JoinHandle, sync::{Arc, atomic::AtomicBool}};
+use std::{thread::{self, JoinHandle}, sync::{Arc, atomic::{AtomicBool, Ordering}}, time::{Instant, Duration}, fmt, path::Path};
 
 use flume::{Sender, Receiver};
-use tokio::runtime::Handle;
+use image::{RgbaImage, ImageFormat};
+use tokio::{runtime::Handle, time::MissedTickBehavior};
 
-use crate::controller::controller_input::ControllerInput;
+use crate::{controller::{controller_input::ControllerInput, input_script::InputScript}, launch_options::LaunchOptions, signals, constants::{WINDOW_INIT_X, WINDOW_INIT_Y}, view::renderer::UNIT_QUAD};
 
-use super::renderer::Vertex;
+use super::{frame::Frame, sprite_manifest::Sprites, software_renderer::SoftwareRenderer, render_statistics::RenderStatistics, frame_recording::FrameRecorder};
 
-pub(crate) async fn go( mut threads_vec: Vec<JoinHandle<()>>, running: Arc<AtomicBool>, controller_sender: Sender<ControllerInput>, vertex_receiver: Receiver<Vec<Vertex>>, _rt: Handle){
-    println!("Did not open a window");
-    loop{
-        
+//drawing a frame on the cpu takes a while, so only every so often a new picture is written
+const SOFTWARE_OUTPUT_INTERVAL: Duration = Duration::from_secs(1);
+//how long shutting down waits for the communication loop to send its last frame
+const LAST_FRAME_TIMEOUT: Duration = Duration::from_secs(1);
+//how long shutting down waits for all threads together before giving up on them
+const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/**
+ * The headless frontend for --no-GUI (and the fallback if no window can be opened). Takes frames from the communication loop at --rate per second
+ * without showing them, until --ticks frames were taken, --duration passed or the running flag was cleared. Then it shuts down the other threads and prints a summary
+ * With --software-output, a frame is drawn by the SoftwareRenderer about once per second. With --script, the inputs of the script are sent to the controller once they are due
+ * With --record, every frame taken is written into a recording
+ * SIGINT and SIGTERM stop it like --ticks would, the process exits with 1 if the threads could not be shut down
+ */
+pub(crate) async fn go( mut threads_vec: Vec<JoinHandle<()>>, running: Arc<AtomicBool>, controller_sender: Sender<ControllerInput>, vertex_receiver: Receiver<Frame>, sprites: Arc<Sprites>, render_statistics: Arc<RenderStatistics>, options: LaunchOptions, rt: Handle){
+    println!("Running headless, taking {} frames per second{}{}",
+        if options.headless_rate > 0.0 { format!("{}", options.headless_rate) } else { "as many".to_string() },
+        options.ticks.map_or(String::new(), |ticks| format!(", stopping after {} frames", ticks)),
+        options.duration.map_or(String::new(), |duration| format!(", stopping after {:.2}s", duration.as_secs_f64())));
+    signals::stop_on_signal(&rt, running.clone());
+    let mut input_script = match options.input_script.as_ref().map(|path| InputScript::load(path)) {
+        Some(Ok(script)) => {
+            println!("Playing input script with {} events", script.len());
+            Some(script)
+        },
+        Some(Err(e)) => {
+            eprintln!("Could not load input script: {}", e);
+            shutdown(&mut threads_vec, &running, controller_sender, &vertex_receiver);
+            std::process::exit(1);
+        },
+        None => None,
+    };
+    //there is no window, the screen space instances are recorded for one of the initial size, just like the software renderer draws them
+    let mut recorder = match options.record.as_ref().map(|path| FrameRecorder::create(path, (WINDOW_INIT_X, WINDOW_INIT_Y))) {
+        Some(Ok(recorder)) => Some(recorder),
+        Some(Err(e)) => {
+            eprintln!("Could not start recording: {}", e);
+            shutdown(&mut threads_vec, &running, controller_sender, &vertex_receiver);
+            std::process::exit(1);
+        },
+        None => None,
+    };
+    let software_renderer = options.software_output.as_ref().map(|_| SoftwareRenderer::new(WINDOW_INIT_X, WINDOW_INIT_Y, sprites));
+    let mut last_output: Option<Instant> = None;
+    let mut summary = HeadlessSummary::default();
+    let started = Instant::now();
+    let mut last_frame = started;
+    let mut ticker = if options.headless_rate > 0.0 {
+        let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / options.headless_rate));
+        //a slow software render should not be followed by a burst of frames to catch up
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Some(ticker)
+    } else {
+        None
+    };
+    while running.load(Ordering::Relaxed){
+        if matches!(options.ticks, Some(ticks) if summary.frames >= ticks) {
+            println!("Took {} frames, stopping", summary.frames);
+            break;
+        }
+        if matches!(options.duration, Some(duration) if started.elapsed() >= duration) {
+            println!("Ran for {:.2}s, stopping", started.elapsed().as_secs_f64());
+            break;
+        }
+        if let Some(ticker) = ticker.as_mut() {
+            ticker.tick().await;
+        }
+        //sent before the frame is taken, so an event at tick N is handled by the controller after N frames, tick 0 before the first one
+        if let Some(script) = input_script.as_mut() {
+            for input in script.take_due(summary.frames, started.elapsed()) {
+                if controller_sender.send(input).is_err() {
+                    println!("The controller stopped, could not send scripted input");
+                }
+                summary.scripted_inputs += 1;
+            }
+            if script.is_finished() {
+                println!("Input script finished after {} frames ({:.2}s)", summary.frames, started.elapsed().as_secs_f64());
+                input_script = None;
+            }
+        }
+        //the frames need to be taken out of the channel, otherwise the communication loop blocks on sending the next one
+        let frame = match vertex_receiver.recv_async().await {
+            Ok(frame) => frame,
+            Err(_) => break,
+        };
+        //nothing is drawn here, but the rate at which frames are taken is still interesting for the debug overlay
+        let frame_time = last_frame.elapsed();
+        render_statistics.record_frame(frame_time);
+        last_frame = Instant::now();
+        summary.record_frame(&frame, frame_time);
+        if let Some(Err(e)) = recorder.as_mut().map(|recorder| recorder.record(&frame)) {
+            println!("{}, stopping the recording", e);
+            recorder = None;
+        }
+        if let (Some(renderer), Some(path)) = (&software_renderer, &options.software_output) {
+            if last_output.map_or(true, |last| last.elapsed() >= SOFTWARE_OUTPUT_INTERVAL) {
+                let render_start = Instant::now();
+                if let Err(e) = save_atomically(&renderer.render(&frame), path) {
+                    println!("Could not write software rendered frame to {}: {}", path.display(), e);
+                }
+                summary.record_software_render(render_start.elapsed());
+                last_output = Some(Instant::now());
+            }
+        }
+    }
+    summary.elapsed = started.elapsed();
+    if let Some(recorder) = recorder {
+        let frames = recorder.frames();
+        match recorder.finish() {
+            Ok(()) => println!("Recorded {} frames to {}", frames, options.record.as_ref().unwrap().display()),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    let shut_down = shutdown(&mut threads_vec, &running, controller_sender, &vertex_receiver);
+    println!("{}", summary);
+    if !shut_down {
+        std::process::exit(1);
+    }
+}
+
+
+/**
+ * stops the model, the controller and the communication loop and waits for them, every frontend ends with this
+ * Returns false if a thread panicked or did not stop within JOIN_TIMEOUT, the caller should exit with a non zero code then
+ */
+pub(crate) fn shutdown(threads_vec: &mut Vec<JoinHandle<()>>, running: &AtomicBool, controller_sender: Sender<ControllerInput>, vertex_receiver: &Receiver<Frame>) -> bool{
+    running.store(false, Ordering::SeqCst);
+    //dropping the only sender makes recv() on the controller thread return an Err
+    drop(controller_sender);
+    //the communication loop might be blocked on sending, taking one more frame lets it see the running flag. It has stopped already if nothing comes
+    let _ = vertex_receiver.recv_timeout(LAST_FRAME_TIMEOUT);
+    //join() can't time out, so the threads are only joined once they are finished. One that hangs is left behind, exiting the process ends it
+    let deadline = Instant::now() + JOIN_TIMEOUT;
+    while !threads_vec.iter().all(|thread| thread.is_finished()) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut clean = true;
+    for cur_thread in threads_vec.drain(..) {
+        let name = cur_thread.thread().name().unwrap_or("unnamed").to_string();
+        if !cur_thread.is_finished() {
+            println!("A thread ({}) did not stop within {:.0}s", name, JOIN_TIMEOUT.as_secs_f64());
+            clean = false;
+        } else if cur_thread.join().is_err() {
+            println!("A thread ({}) panicked before it could be joined", name);
+            clean = false;
+        }
+    }
+    if clean {
+        println!("Gracefully exiting ...");
+    }
+    clean
+}
+
+/**
+ * writes into a temporary file next to path first and renames it afterwards, so whoever watches the file never sees half a png
+ */
+fn save_atomically(image: &RgbaImage, path: &Path) -> image::ImageResult<()>{
+    let format = ImageFormat::from_path(path).unwrap_or(ImageFormat::Png);
+    let file_name = path.file_name().map_or("software_output".into(), |name| name.to_string_lossy());
+    let temporary = path.with_file_name(format!(".{}.tmp", file_name));
+    image.save_with_format(&temporary, format)?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+
+/**
+ * collected while the headless runner takes frames, printed once it stops
+ */
+#[derive(Debug, Default)]
+struct HeadlessSummary{
+    frames: u64,
+    elapsed: Duration,
+    //everything that would have been drawn: world, particles and screen space instances
+    total_vertices: u64,
+    max_vertices: u64,
+    //sent to the controller from the input script
+    scripted_inputs: u64,
+    //time between taking two frames
+    min_frame_time: Option<Duration>,
+    max_frame_time: Duration,
+    total_frame_time: Duration,
+    software_renders: u32,
+    software_render_time: Duration,
+}
+
+impl HeadlessSummary {
+    fn record_frame(&mut self, frame: &Frame, frame_time: Duration){
+        let vertices = ((frame.instances.len() + frame.particles.len() + frame.screen_instances.len()) * UNIT_QUAD.len()) as u64;
+        self.frames += 1;
+        self.total_vertices += vertices;
+        self.max_vertices = self.max_vertices.max(vertices);
+        self.min_frame_time = Some(self.min_frame_time.map_or(frame_time, |min| min.min(frame_time)));
+        self.max_frame_time = self.max_frame_time.max(frame_time);
+        self.total_frame_time += frame_time;
+    }
+
+    fn record_software_render(&mut self, render_time: Duration){
+        self.software_renders += 1;
+        self.software_render_time += render_time;
+    }
+}
+
+impl fmt::Display for HeadlessSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        let per_frame = |total: f64| if self.frames > 0 { total / self.frames as f64 } else { 0.0 };
+        writeln!(f, "Headless run summary")?;
+        writeln!(f, "  time: {:.2}s", seconds)?;
+        writeln!(f, "  frames: {} ({:.1} per second)", self.frames, if seconds > 0.0 { self.frames as f64 / seconds } else { 0.0 })?;
+        writeln!(f, "  vertices per frame: {:.0} on average, {} at most", per_frame(self.total_vertices as f64), self.max_vertices)?;
+        write!(f, "  time between frames: {:.2} ms min, {:.2} ms average, {:.2} ms max",
+            self.min_frame_time.unwrap_or_default().as_secs_f64() * 1000.0, per_frame(self.total_frame_time.as_secs_f64() * 1000.0), self.max_frame_time.as_secs_f64() * 1000.0)?;
+        if self.scripted_inputs > 0 {
+            write!(f, "\n  scripted inputs: {}", self.scripted_inputs)?;
+        }
+        if self.software_renders > 0 {
+            write!(f, "\n  software renders: {} ({:.1} ms on average)", self.software_renders, self.software_render_time.as_secs_f64() * 1000.0 / self.software_renders as f64)?;
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
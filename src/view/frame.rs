@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+
+use super::{renderer::Instance, lighting::LightInstance, post_processing::PostEffect};
+
+
+/**
+ * Everything the view needs to draw one frame. This is what gets sent from the communication loop to the renderer
+ * The instances are in world space and only get rebuilt if the model changed, so the Arc is shared between frames as long as nothing happened
+ */
+#[derive(Clone, Debug)]
+pub struct Frame{
+    pub instances: Arc<Vec<Instance>>,
+    //drawn on top of the world with CameraUniform::screen_space, so they are in pixels with 0/0 being the top left corner of the window
+    pub screen_instances: Arc<Vec<Instance>>,
+    //in world space, drawn after the first effects_end instances, so they end up in RenderLayer::Effects
+    pub particles: Arc<Vec<Instance>>,
+    pub effects_end: usize,
+    pub camera: CameraUniform,
+    //in world space like the instances, they are added up into the light map that is multiplied onto the world
+    pub lights: Arc<Vec<LightInstance>>,
+    //rgba the light map starts out with, see Model::ambient_light
+    pub ambient_light: [f32; 4],
+    //the world is only drawn into this part of the window, see ViewportPolicy. Screen instances always use the whole window
+    pub viewport: Viewport,
+    //full screen effects applied to the world in this order before the screen instances are drawn, empty draws the world straight into the window
+    pub post_effects: Arc<Vec<PostEffect>>,
+    //the view should save this frame as an image, see screenshot_action
+    pub screenshot: bool,
+}
+
+
+/**
+ * a rectangle of the window in physical pixels, 0/0 is the top left corner
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport{
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn full(window_dimensions: (u32, u32)) -> Self{
+        Viewport { x: 0.0, y: 0.0, width: window_dimensions.0 as f32, height: window_dimensions.1 as f32 }
+    }
+
+    /**
+     * pixel position in the window -> position relative to the viewport, -1/-1 is its top left corner and 1/1 the bottom right one
+     */
+    pub fn to_relative(&self, position: (f32, f32)) -> (f32, f32){
+        ((position.0 - self.x) / self.width * 2.0 - 1.0, (position.1 - self.y) / self.height * 2.0 - 1.0)
+    }
+}
+
+
+// This is uploaded into a uniform buffer as is, so it needs to be laid out like in C (see Vertex)
+// The layout has to match the CameraUniform struct in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+pub struct CameraUniform{
+    //center of the screen in world coordinates
+    pub position: [f32; 2],
+    //half of the visible height in world coordinates
+    pub zoom: f32,
+    //width / height of the visible area
+    pub aspect: f32,
+}
+
+impl CameraUniform {
+    /**
+     * window_dimensions_ingame holds half the visible width and height, just like it is stored in the GameState
+     */
+    pub fn new(camera_pos: (f64, f64), window_dimensions_ingame: (f64, f64)) -> Self{
+        CameraUniform {
+            position: [camera_pos.0 as f32, camera_pos.1 as f32],
+            zoom: window_dimensions_ingame.1 as f32,
+            aspect: (window_dimensions_ingame.0 / window_dimensions_ingame.1) as f32,
+        }
+    }
+
+    /**
+     * world space -> clip space, the same calculation as in vs_main. -1/-1 is the bottom left corner of the viewport and 1/1 the top right one, so y is flipped
+     */
+    pub fn to_clip_space(self, position: [f32; 2]) -> [f32; 2]{
+        let half_extent = [self.zoom * self.aspect, self.zoom];
+        [(position[0] - self.position[0]) / half_extent[0], -(position[1] - self.position[1]) / half_extent[1]]
+    }
+
+    /**
+     * a camera that shows exactly the window, one world unit is one pixel. Used for text and everything else that does not move with the world
+     */
+    pub fn screen_space(width: u32, height: u32) -> Self{
+        CameraUniform {
+            position: [width as f32 / 2.0, height as f32 / 2.0],
+            zoom: height as f32 / 2.0,
+            aspect: width as f32 / height as f32,
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use std::{fmt, str::FromStr, fs::File, io::{self, BufWriter, Write}, path::{Path, PathBuf}, time::Instant};
+
+use bytemuck::{Pod, Zeroable};
+
+use super::{frame::{Frame, CameraUniform}, renderer::{Vertex, Instance}, lighting::LightInstance};
+
+
+const MAGIC: [u8; 8] = *b"DUGFRAME";
+//bump this whenever the layout or the meaning of anything below changes. 2: positions are in clip space instead of world space
+const VERSION: u32 = 2;
+
+
+/**
+ * Recordings are a RecordingHeader followed by the frames until the end of the file. Every frame is a RecordedFrameHeader followed by its vertices
+ * (world, particles, screen space, in that order, counts in the frame header) and its lights. The positions of the vertices are in clip space, the lights stay in world space. Everything is written as it is in memory (bytemuck), so recordings only work on machines with the same endianness
+ */
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+struct RecordingHeader{
+    magic: [u8; 8],
+    version: u32,
+    //sizes of the structs, a recording from a build with another layout is rejected instead of read as garbage
+    frame_header_size: u32,
+    vertex_size: u32,
+    light_size: u32,
+    //the camera the screen space vertices were transformed with, it only depends on the size of the window
+    screen_camera: CameraUniform,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+struct RecordedFrameHeader{
+    //seconds since the recording started, only informative, it is never compared
+    time: f32,
+    camera: CameraUniform,
+    //x, y, width, height, see Frame::viewport
+    viewport: [f32; 4],
+    ambient_light: [f32; 4],
+    world_vertices: u32,
+    //the particles were drawn after this many world vertices, see Frame::effects_end
+    effects_end_vertex: u32,
+    particle_vertices: u32,
+    screen_vertices: u32,
+    lights: u32,
+}
+
+
+#[derive(Debug)]
+pub enum RecordingError{
+    Io{ path: PathBuf, source: io::Error },
+    NotARecording{ path: PathBuf },
+    UnsupportedVersion{ path: PathBuf, version: u32 },
+    //the file ends in the middle of a frame, e.g. because the game was killed while recording. frames is how many complete frames were read
+    Truncated{ path: PathBuf, frames: usize },
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io { path, source } => write!(f, "could not access recording {}: {}", path.display(), source),
+            RecordingError::NotARecording { path } => write!(f, "{} is not a frame recording", path.display()),
+            RecordingError::UnsupportedVersion { path, version } => write!(f, "recording {} has version {}, this build reads version {} with the same struct sizes", path.display(), version, VERSION),
+            RecordingError::Truncated { path, frames } => write!(f, "recording {} ends in the middle of frame {}", path.display(), frames),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecordingError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+
+/**
+ * Writes the frames the headless runner takes into a file (--record), they can be compared later with the diff subcommand
+ * The instances are expanded into their corners (Instance::to_vertices) and moved into clip space with the camera (CameraUniform::to_clip_space),
+ * so the positions are the ones vs_main hands to the gpu and the recording covers the vertex math, the culling and the camera
+ */
+pub struct FrameRecorder{
+    path: PathBuf,
+    writer: BufWriter<File>,
+    screen_camera: CameraUniform,
+    started: Instant,
+    frames: usize,
+}
+
+impl FrameRecorder {
+    /**
+     * window_size is what the screen space instances are laid out for, in logical pixels like CameraUniform::screen_space expects
+     */
+    pub fn create(path: &Path, window_size: (u32, u32)) -> Result<Self, RecordingError>{
+        let io_error = |source| RecordingError::Io { path: path.to_path_buf(), source };
+        let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        let header = RecordingHeader {
+            magic: MAGIC,
+            version: VERSION,
+            frame_header_size: std::mem::size_of::<RecordedFrameHeader>() as u32,
+            vertex_size: std::mem::size_of::<Vertex>() as u32,
+            light_size: std::mem::size_of::<LightInstance>() as u32,
+            screen_camera: CameraUniform::screen_space(window_size.0, window_size.1),
+        };
+        writer.write_all(bytemuck::bytes_of(&header)).map_err(io_error)?;
+        Ok(FrameRecorder { path: path.to_path_buf(), writer, screen_camera: header.screen_camera, started: Instant::now(), frames: 0 })
+    }
+
+    pub fn record(&mut self, frame: &Frame) -> Result<(), RecordingError>{
+        let to_vertices = |instances: &[Instance], camera: &CameraUniform| instances.iter().flat_map(|instance| instance.to_vertices()).map(|mut vertex| {
+            vertex.position = camera.to_clip_space(vertex.position);
+            vertex
+        }).collect::<Vec<Vertex>>();
+        let world = to_vertices(&frame.instances, &frame.camera);
+        let particles = to_vertices(&frame.particles, &frame.camera);
+        let screen = to_vertices(&frame.screen_instances, &self.screen_camera);
+        let vertices_per_instance = world.len() / frame.instances.len().max(1);
+        let header = RecordedFrameHeader {
+            time: self.started.elapsed().as_secs_f32(),
+            camera: frame.camera,
+            viewport: [frame.viewport.x, frame.viewport.y, frame.viewport.width, frame.viewport.height],
+            ambient_light: frame.ambient_light,
+            world_vertices: world.len() as u32,
+            effects_end_vertex: (frame.effects_end.min(frame.instances.len()) * vertices_per_instance) as u32,
+            particle_vertices: particles.len() as u32,
+            screen_vertices: screen.len() as u32,
+            lights: frame.lights.len() as u32,
+        };
+        let io_error = |source| RecordingError::Io { path: self.path.clone(), source };
+        self.writer.write_all(bytemuck::bytes_of(&header)).map_err(io_error)?;
+        for vertices in [&world, &particles, &screen] {
+            self.writer.write_all(bytemuck::cast_slice(vertices)).map_err(io_error)?;
+        }
+        self.writer.write_all(bytemuck::cast_slice(&frame.lights)).map_err(io_error)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> usize{
+        self.frames
+    }
+
+    pub fn finish(mut self) -> Result<(), RecordingError>{
+        self.writer.flush().map_err(|source| RecordingError::Io { path: self.path.clone(), source })
+    }
+}
+
+
+struct RecordedFrame{
+    header: RecordedFrameHeader,
+    world: Vec<Vertex>,
+    particles: Vec<Vertex>,
+    screen: Vec<Vertex>,
+    lights: Vec<LightInstance>,
+}
+
+fn read_recording(path: &Path) -> Result<Vec<RecordedFrame>, RecordingError>{
+    let bytes = std::fs::read(path).map_err(|source| RecordingError::Io { path: path.to_path_buf(), source })?;
+    let mut reader = ByteReader { bytes: &bytes, position: 0 };
+    let header: RecordingHeader = reader.read().ok_or_else(|| RecordingError::NotARecording { path: path.to_path_buf() })?;
+    if header.magic != MAGIC {
+        return Err(RecordingError::NotARecording { path: path.to_path_buf() });
+    }
+    if header.version != VERSION || header.frame_header_size as usize != std::mem::size_of::<RecordedFrameHeader>()
+        || header.vertex_size as usize != std::mem::size_of::<Vertex>() || header.light_size as usize != std::mem::size_of::<LightInstance>() {
+        return Err(RecordingError::UnsupportedVersion { path: path.to_path_buf(), version: header.version });
+    }
+
+    let mut frames = Vec::new();
+    while !reader.is_at_end() {
+        let truncated = || RecordingError::Truncated { path: path.to_path_buf(), frames: frames.len() };
+        let header: RecordedFrameHeader = reader.read().ok_or_else(truncated)?;
+        let world = reader.read_many(header.world_vertices as usize).ok_or_else(truncated)?;
+        let particles = reader.read_many(header.particle_vertices as usize).ok_or_else(truncated)?;
+        let screen = reader.read_many(header.screen_vertices as usize).ok_or_else(truncated)?;
+        let lights = reader.read_many(header.lights as usize).ok_or_else(truncated)?;
+        frames.push(RecordedFrame { header, world, particles, screen, lights });
+    }
+    Ok(frames)
+}
+
+//the file is read as plain bytes, which are not aligned for the structs, so everything is copied out with pod_read_unaligned
+struct ByteReader<'a>{
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn read<T: Pod>(&mut self) -> Option<T>{
+        self.take(std::mem::size_of::<T>()).map(bytemuck::pod_read_unaligned)
+    }
+
+    fn read_many<T: Pod>(&mut self, count: usize) -> Option<Vec<T>>{
+        let size = std::mem::size_of::<T>();
+        //the count comes from the file, a broken one must not overflow here
+        let bytes = self.take(size.checked_mul(count)?)?;
+        Some(bytes.chunks_exact(size).map(bytemuck::pod_read_unaligned).collect())
+    }
+
+    fn take(&mut self, length: usize) -> Option<&'a [u8]>{
+        let end = self.position.checked_add(length)?;
+        let bytes = self.bytes.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    fn is_at_end(&self) -> bool{
+        self.position >= self.bytes.len()
+    }
+}
+
+
+/**
+ * what counts as the same when comparing two recordings
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions{
+    //for positions (in clip space, the viewport is 2 wide), the camera, texture coordinates and light radii
+    pub tolerance: f32,
+    //for tints and light colors
+    pub color_tolerance: f32,
+    //particles are random, so two runs never have the same ones
+    pub ignore_particles: bool,
+    //the debug overlay shows timings, which are never the same either
+    pub ignore_screen: bool,
+    //at most this many differing frames are printed, the rest is only counted
+    pub max_reports: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions { tolerance: 1e-4, color_tolerance: 1e-3, ignore_particles: false, ignore_screen: false, max_reports: 10 }
+    }
+}
+
+/**
+ * `dug diff <a> <b> [--tolerance 0.0001] [--color-tolerance 0.001] [--ignore-particles] [--ignore-screen] [--max-reports 10]`
+ * Returns the exit code: 0 if the recordings match, 1 if they differ, 2 if they could not be read
+ */
+pub fn diff_command(args: &[String]) -> i32{
+    //everything that is neither an option nor the value of one
+    let paths: Vec<&String> = args.iter().enumerate()
+        .filter(|(i, arg)| !(arg.starts_with("--") || *i > 0 && matches!(args[i - 1].as_str(), "--tolerance" | "--color-tolerance" | "--max-reports")))
+        .map(|(_, arg)| arg)
+        .collect();
+    if paths.len() != 2 {
+        eprintln!("usage: dug diff <recording a> <recording b> [--tolerance 0.0001] [--color-tolerance 0.001] [--ignore-particles] [--ignore-screen] [--max-reports 10]");
+        return 2;
+    }
+    let defaults = DiffOptions::default();
+    let options = DiffOptions {
+        tolerance: number_of(args, "--tolerance", defaults.tolerance),
+        color_tolerance: number_of(args, "--color-tolerance", defaults.color_tolerance),
+        ignore_particles: args.iter().any(|arg| arg == "--ignore-particles"),
+        ignore_screen: args.iter().any(|arg| arg == "--ignore-screen"),
+        max_reports: number_of(args, "--max-reports", defaults.max_reports),
+    };
+
+    let (a, b) = match (read_recording(Path::new(paths[0])), read_recording(Path::new(paths[1]))) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let mut differing_frames = 0;
+    for (index, (frame_a, frame_b)) in a.iter().zip(b.iter()).enumerate() {
+        let differences = diff_frames(frame_a, frame_b, &options);
+        if differences.is_empty() {
+            continue;
+        }
+        differing_frames += 1;
+        if differing_frames <= options.max_reports {
+            println!("frame {}: {}", index, differences.join(", "));
+        }
+    }
+    if differing_frames > options.max_reports {
+        println!("... and {} more differing frames", differing_frames - options.max_reports);
+    }
+    if a.len() != b.len() {
+        println!("{} has {} frames, {} has {}, only the first {} were compared", paths[0], a.len(), paths[1], b.len(), a.len().min(b.len()));
+    }
+    if differing_frames == 0 && a.len() == b.len() {
+        println!("{} frames, no differences", a.len());
+        0
+    } else {
+        println!("{} of {} compared frames differ", differing_frames, a.len().min(b.len()));
+        1
+    }
+}
+
+//the value after name, or default if it is missing or not a valid T (which also rules out negative --max-reports)
+fn number_of<T: FromStr + fmt::Display>(args: &[String], name: &str, default: T) -> T{
+    match args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)) {
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("{} expects a number, got {}, using {}", name, value, default);
+            default
+        }),
+        None => default,
+    }
+}
+
+/**
+ * a short description of everything that differs between the two frames, empty if they match
+ */
+fn diff_frames(a: &RecordedFrame, b: &RecordedFrame, options: &DiffOptions) -> Vec<String>{
+    let mut differences = Vec::new();
+    let camera_a = [a.header.camera.position[0], a.header.camera.position[1], a.header.camera.zoom, a.header.camera.aspect];
+    let camera_b = [b.header.camera.position[0], b.header.camera.position[1], b.header.camera.zoom, b.header.camera.aspect];
+    let camera_difference = max_difference(&camera_a, &camera_b);
+    if camera_difference > options.tolerance {
+        differences.push(format!("camera differs by {:.6}", camera_difference));
+    }
+    if max_difference(&a.header.viewport, &b.header.viewport) > options.tolerance {
+        differences.push(format!("viewport {:?} vs {:?}", a.header.viewport, b.header.viewport));
+    }
+    if max_difference(&a.header.ambient_light, &b.header.ambient_light) > options.color_tolerance {
+        differences.push("ambient light differs".to_string());
+    }
+    if a.header.effects_end_vertex != b.header.effects_end_vertex {
+        differences.push(format!("particles drawn after vertex {} vs {}", a.header.effects_end_vertex, b.header.effects_end_vertex));
+    }
+
+    let mut compare_vertices = |name: &str, a: &[Vertex], b: &[Vertex]| {
+        if a.len() != b.len() {
+            differences.push(format!("{} vertices {} vs {}", name, a.len(), b.len()));
+            return;
+        }
+        let mut differing = 0;
+        let mut max_position_difference: f32 = 0.0;
+        for (vertex_a, vertex_b) in a.iter().zip(b.iter()) {
+            let position_difference = max_difference(&vertex_a.position, &vertex_b.position);
+            max_position_difference = max_position_difference.max(position_difference);
+            if position_difference > options.tolerance || vertex_a.tex_i != vertex_b.tex_i
+                || max_difference(&vertex_a.tex_coords, &vertex_b.tex_coords) > options.tolerance || max_difference(&vertex_a.tint, &vertex_b.tint) > options.color_tolerance {
+                differing += 1;
+            }
+        }
+        if differing > 0 {
+            differences.push(format!("{} of {} {} vertices differ (positions by up to {:.6})", differing, a.len(), name, max_position_difference));
+        }
+    };
+    compare_vertices("world", &a.world, &b.world);
+    if !options.ignore_particles {
+        compare_vertices("particle", &a.particles, &b.particles);
+    }
+    if !options.ignore_screen {
+        compare_vertices("screen", &a.screen, &b.screen);
+    }
+
+    if a.lights.len() != b.lights.len() {
+        differences.push(format!("lights {} vs {}", a.lights.len(), b.lights.len()));
+    } else {
+        let differing = a.lights.iter().zip(b.lights.iter()).filter(|(light_a, light_b)| {
+            max_difference(&light_a.position, &light_b.position) > options.tolerance || (light_a.radius - light_b.radius).abs() > options.tolerance
+                || (light_a.falloff - light_b.falloff).abs() > options.tolerance || max_difference(&light_a.color, &light_b.color) > options.color_tolerance
+        }).count();
+        if differing > 0 {
+            differences.push(format!("{} of {} lights differ", differing, a.lights.len()));
+        }
+    }
+    differences
+}
+
+fn max_difference(a: &[f32], b: &[f32]) -> f32{
+    a.iter().zip(b.iter()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::view::{frame::{Frame, CameraUniform, Viewport}, renderer::Instance, lighting::LightInstance};
+
+    use super::{FrameRecorder, RecordingError, DiffOptions, read_recording, diff_frames};
+
+    fn test_frame(camera_x: f32, tint: f32) -> Frame{
+        let instance = |position: [f32; 2]| Instance { position, size: [0.5, 0.5], tex_i: 1, uv_rect: [0.0, 0.0, 1.0, 1.0], tint: [tint, 1.0, 1.0, 1.0], rotation: 0.3, pivot: [0.5, 1.0] };
+        Frame {
+            instances: Arc::new(vec![instance([0.0, 0.0]), instance([2.0, 1.0]), instance([-1.0, 3.0])]),
+            screen_instances: Arc::new(vec![instance([10.0, 10.0])]),
+            particles: Arc::new(vec![instance([0.5, 0.5])]),
+            effects_end: 2,
+            camera: CameraUniform { position: [camera_x, 1.0], zoom: 2.0, aspect: 16.0 / 9.0 },
+            lights: Arc::new(vec![LightInstance { position: [1.0, 1.0], radius: 2.0, falloff: 1.0, color: [1.0, 0.5, 0.25, 1.0] }]),
+            ambient_light: [0.3, 0.3, 0.3, 1.0],
+            viewport: Viewport::full((800, 450)),
+            post_effects: Arc::new(Vec::new()),
+            screenshot: false,
+        }
+    }
+
+    fn record(name: &str, frames: &[Frame]) -> std::path::PathBuf{
+        let path = std::env::temp_dir().join(format!("dug_{}_{}.bin", name, std::process::id()));
+        let mut recorder = FrameRecorder::create(&path, (800, 450)).unwrap();
+        for frame in frames {
+            recorder.record(frame).unwrap();
+        }
+        recorder.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn recording_round_trip(){
+        let frame = test_frame(0.0, 1.0);
+        let path = record("round_trip", &[frame.clone(), test_frame(0.25, 1.0)]);
+        let recorded = read_recording(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(recorded.len(), 2);
+        let first = &recorded[0];
+        let expected_world: Vec<_> = frame.instances.iter().flat_map(|instance| instance.to_vertices()).collect();
+        assert_eq!(first.world.len(), expected_world.len());
+        for (recorded, expected) in first.world.iter().zip(expected_world.iter()) {
+            assert_eq!(recorded.position, frame.camera.to_clip_space(expected.position));
+            assert_eq!((recorded.tex_i, recorded.tex_coords, recorded.tint), (expected.tex_i, expected.tex_coords, expected.tint));
+        }
+        //the screen instances use the camera of the window instead
+        assert_eq!(first.screen[0].position, CameraUniform::screen_space(800, 450).to_clip_space(frame.screen_instances[0].to_vertices()[0].position));
+        assert_eq!(first.particles.len(), 6);
+        assert_eq!(first.screen.len(), 6);
+        assert_eq!(first.header.effects_end_vertex, 12);
+        assert_eq!(first.lights.len(), 1);
+        assert_eq!(first.lights[0].color, [1.0, 0.5, 0.25, 1.0]);
+        assert_eq!(first.header.camera.position, [0.0, 1.0]);
+        assert_eq!(recorded[1].header.camera.position, [0.25, 1.0]);
+
+        assert!(diff_frames(&recorded[0], &recorded[0], &DiffOptions::default()).is_empty());
+        assert!(!diff_frames(&recorded[0], &recorded[1], &DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn clip_space_matches_the_shader(){
+        let camera = CameraUniform { position: [1.0, 2.0], zoom: 2.0, aspect: 2.0 };
+        assert_eq!(camera.to_clip_space([1.0, 2.0]), [0.0, 0.0]);
+        assert_eq!(camera.to_clip_space([5.0, 2.0]), [1.0, 0.0]);
+        //y points down in the world, but up in clip space
+        assert_eq!(camera.to_clip_space([1.0, 0.0]), [0.0, 1.0]);
+        assert_eq!(camera.to_clip_space([-3.0, 4.0]), [-1.0, -1.0]);
+        //the top left pixel of the window is the top left corner of clip space
+        assert_eq!(CameraUniform::screen_space(800, 450).to_clip_space([0.0, 0.0]), [-1.0, 1.0]);
+    }
+
+    #[test]
+    fn diff_respects_tolerances(){
+        let path = record("tolerances", &[test_frame(0.0, 1.0), test_frame(0.00005, 1.0), test_frame(0.0, 0.9995), test_frame(0.0, 0.99)]);
+        let recorded = read_recording(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let options = DiffOptions::default();
+
+        //a camera within the tolerance moves every vertex by even less, the visible area is more than 2 units wide
+        assert!(diff_frames(&recorded[0], &recorded[1], &options).is_empty());
+        assert!(!diff_frames(&recorded[0], &recorded[1], &DiffOptions { tolerance: 1e-6, ..options }).is_empty());
+        //tints use the color tolerance
+        assert!(diff_frames(&recorded[0], &recorded[2], &options).is_empty());
+        let differences = diff_frames(&recorded[0], &recorded[3], &options);
+        assert!(differences.iter().any(|difference| difference.contains("world vertices differ")), "{:?}", differences);
+        //the particles and the screen instances carry the tint as well, they can be left out
+        assert_eq!(diff_frames(&recorded[0], &recorded[3], &DiffOptions { ignore_particles: true, ignore_screen: true, ..options }).len(), 1);
+    }
+
+    #[test]
+    fn broken_counts_are_truncated(){
+        let path = record("broken", &[test_frame(0.0, 1.0)]);
+        let mut bytes = std::fs::read(&path).unwrap();
+        //world_vertices of the first frame, right after the file header, the time, the camera, the viewport and the ambient light
+        let offset = std::mem::size_of::<super::RecordingHeader>() + 4 + 16 + 16 + 16;
+        bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_ne_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        let result = read_recording(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(RecordingError::Truncated { frames: 0, .. })));
+    }
+}
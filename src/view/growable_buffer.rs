@@ -0,0 +1,101 @@
+use std::marker::PhantomData;
+
+use bytemuck::Pod;
+use wgpu::{Device, Queue, Buffer, BufferUsages, BufferSlice};
+
+
+//capacity of a freshly created buffer, in elements
+const INITIAL_CAPACITY: u64 = 1024;
+
+
+/**
+ * A gpu buffer that is kept alive between frames. New data is written into it with queue.write_buffer, the buffer is only reallocated if the data does not fit anymore.
+ * Growth policy: the capacity is doubled until the data fits (starting at INITIAL_CAPACITY), it never shrinks
+ */
+pub struct GrowableBuffer<T: Pod>{
+    buffer: Buffer,
+    label: &'static str,
+    usage: BufferUsages,
+    len: u64,
+    stats: BufferStats,
+    _element: PhantomData<T>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BufferStats{
+    //in elements, not bytes
+    pub capacity: u64,
+    pub len: u64,
+    pub reallocations: u32,
+    pub writes: u64,
+}
+
+impl BufferStats {
+    #[allow(dead_code)]
+    pub fn capacity_bytes(&self, element_size: usize) -> u64{
+        self.capacity * element_size as u64
+    }
+}
+
+
+impl<T: Pod> GrowableBuffer<T> {
+
+    pub fn new(device: &Device, label: &'static str, usage: BufferUsages) -> Self{
+        //COPY_DST is needed for queue.write_buffer
+        let usage = usage | BufferUsages::COPY_DST;
+        GrowableBuffer {
+            buffer: create_buffer::<T>(device, label, usage, INITIAL_CAPACITY),
+            label,
+            usage,
+            len: 0,
+            stats: BufferStats { capacity: INITIAL_CAPACITY, ..Default::default() },
+            _element: PhantomData,
+        }
+    }
+
+    pub fn write(&mut self, device: &Device, queue: &Queue, data: &[T]){
+        let needed = data.len() as u64;
+        if needed > self.stats.capacity {
+            let mut new_capacity = self.stats.capacity.max(INITIAL_CAPACITY);
+            while new_capacity < needed {
+                new_capacity *= 2;
+            }
+            self.stats.reallocations += 1;
+            log::debug!("Growing {} from {} to {} elements ({} bytes), reallocation #{}", self.label, self.stats.capacity, new_capacity, new_capacity * std::mem::size_of::<T>() as u64, self.stats.reallocations);
+            //the old buffer is dropped here, wgpu keeps it alive until the gpu is done with it
+            self.buffer = create_buffer::<T>(device, self.label, self.usage, new_capacity);
+            self.stats.capacity = new_capacity;
+        }
+        if !data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+        }
+        self.len = needed;
+        self.stats.len = needed;
+        self.stats.writes += 1;
+    }
+
+    pub fn len(&self) -> u64{
+        self.len
+    }
+
+    /**
+     * only the part of the buffer that was written to in the last write call
+     */
+    pub fn slice(&self) -> BufferSlice<'_>{
+        self.buffer.slice(..(self.len * std::mem::size_of::<T>() as u64).max(std::mem::size_of::<T>() as u64))
+    }
+
+    pub fn stats(&self) -> BufferStats{
+        self.stats
+    }
+}
+
+
+fn create_buffer<T: Pod>(device: &Device, label: &'static str, usage: BufferUsages, capacity: u64) -> Buffer{
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: capacity * std::mem::size_of::<T>() as u64,
+        usage,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,80 @@
+// Light map pass: every light is a quad around its center, the lights are added up on top of the ambient light
+
+// has to match CameraUniform in frame.rs
+struct CameraUniform {
+    position: vec2<f32>,
+    zoom: f32,
+    aspect: f32,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// one corner of the unit quad, 0/0 is top left and 1/1 bottom right
+struct VertexInput {
+    @location(0) corner: vec2<f32>,
+};
+
+// has to match LightInstance in lighting.rs
+struct LightInput {
+    @location(1) position: vec2<f32>,
+    @location(2) radius: f32,
+    @location(3) falloff: f32,
+    @location(4) color: vec4<f32>,
+};
+
+struct LightOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1..1 over the quad, so its length is the distance to the center relative to the radius
+    @location(0) local: vec2<f32>,
+    @location(1) falloff: f32,
+    @location(2) color: vec4<f32>,
+};
+
+@vertex
+fn vs_light(
+    model: VertexInput,
+    light: LightInput,
+) -> LightOutput {
+    var out: LightOutput;
+    out.local = model.corner * 2.0 - 1.0;
+    let world_position = light.position + out.local * light.radius;
+    // same camera transformation as vs_main in shader.wgsl
+    let half_extent = vec2<f32>(camera.zoom * camera.aspect, camera.zoom);
+    let relative = (world_position - camera.position) / half_extent;
+    out.clip_position = vec4<f32>(relative.x, -relative.y, 0.0, 1.0);
+    out.falloff = light.falloff;
+    out.color = light.color;
+    return out;
+}
+
+@fragment
+fn fs_light(in: LightOutput) -> @location(0) vec4<f32> {
+    let attenuation = pow(clamp(1.0 - length(in.local), 0.0, 1.0), in.falloff);
+    return vec4<f32>(in.color.rgb * attenuation, 0.0);
+}
+
+
+// Composite pass: the finished light map is multiplied onto the scene, this is done with the blend state, the shader only reads the light map
+
+@group(0) @binding(0)
+var light_map: texture_2d<f32>;
+
+struct CompositeOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// one triangle that covers the whole viewport, no vertex buffer needed
+@vertex
+fn vs_composite(@builtin(vertex_index) vertex_index: u32) -> CompositeOutput {
+    var out: CompositeOutput;
+    let x = f32(i32(vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(vertex_index >> 1u) * 4 - 1);
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_composite(in: CompositeOutput) -> @location(0) vec4<f32> {
+    // the light map has the size of the window, so the pixel can be read directly
+    return textureLoad(light_map, vec2<i32>(in.clip_position.xy), 0);
+}
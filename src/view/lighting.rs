@@ -0,0 +1,247 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{include_wgsl, BindGroup, BindGroupLayout, BindGroupLayoutDescriptor, CommandEncoder, Device, Queue, RenderPass, RenderPipeline, TextureView};
+
+use super::{growable_buffer::GrowableBuffer, renderer::QuadVertex};
+
+
+//float, so overlapping lights can add up above 1.0 without being clamped before they are multiplied onto the scene
+const LIGHT_MAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+
+// One light in the light map pass, built by PointLight::construct_instance. Drawn as a quad of 2 * radius around position
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+pub struct LightInstance {
+    pub(crate) position: [f32; 2],
+    pub(crate) radius: f32,
+    pub(crate) falloff: f32,
+    //rgb already multiplied with the intensity, a is unused
+    pub(crate) color: [f32; 4],
+}
+
+impl LightInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LightInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() + std::mem::size_of::<f32>()) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() + std::mem::size_of::<f32>() * 2) as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ]
+        }
+    }
+
+    /**
+     * how much light this adds at the given point in world space, the same calculation as fs_light in light.wgsl
+     */
+    pub fn light_at(&self, point: [f32; 2]) -> [f32; 3]{
+        let distance = ((point[0] - self.position[0]).powi(2) + (point[1] - self.position[1]).powi(2)).sqrt() / self.radius;
+        let attenuation = (1.0 - distance).clamp(0.0, 1.0).powf(self.falloff);
+        [self.color[0] * attenuation, self.color[1] * attenuation, self.color[2] * attenuation]
+    }
+}
+
+
+/**
+ * Draws the lights into a light map with the size of the window and multiplies it onto the world afterwards.
+ * The light map starts out as the ambient light, every light is added on top of it. Screen space instances are drawn after the composite, so text stays readable in the dark
+ */
+pub struct LightRenderer{
+    light_pipeline: RenderPipeline,
+    composite_pipeline: RenderPipeline,
+    composite_layout: BindGroupLayout,
+    light_map: TextureView,
+    composite_bind_group: BindGroup,
+    light_buffer: GrowableBuffer<LightInstance>,
+}
+
+impl LightRenderer {
+
+    pub fn new(device: &Device, surface_format: wgpu::TextureFormat, size: (u32, u32), camera_bind_group_layout: &BindGroupLayout) -> Self{
+        let shader = device.create_shader_module(&include_wgsl!("light.wgsl"));
+
+        let light_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Light Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let light_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Light Pipeline"),
+            layout: Some(&light_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_light",
+                buffers: &[QuadVertex::desc(), LightInstance::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_light",
+                targets: &[wgpu::ColorTargetState {
+                    format: LIGHT_MAP_FORMAT,
+                    //lights add up
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent { src_factor: wgpu::BlendFactor::One, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add },
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::COLOR,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState { cull_mode: None, ..Default::default() },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let composite_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        //only read with textureLoad, so no sampler is needed
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("light_map_bind_group_layout"),
+        });
+        let composite_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Light Composite Pipeline Layout"),
+            bind_group_layouts: &[&composite_layout],
+            push_constant_ranges: &[],
+        });
+        let composite_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Light Composite Pipeline"),
+            layout: Some(&composite_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_composite",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_composite",
+                targets: &[wgpu::ColorTargetState {
+                    format: surface_format,
+                    //scene * light map, the alpha of the scene stays as it is
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent { src_factor: wgpu::BlendFactor::Dst, dst_factor: wgpu::BlendFactor::Zero, operation: wgpu::BlendOperation::Add },
+                        alpha: wgpu::BlendComponent { src_factor: wgpu::BlendFactor::Zero, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState { cull_mode: None, ..Default::default() },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let light_map = create_light_map(device, size);
+        let composite_bind_group = create_composite_bind_group(device, &composite_layout, &light_map);
+        LightRenderer {
+            light_pipeline,
+            composite_pipeline,
+            composite_layout,
+            light_map,
+            composite_bind_group,
+            light_buffer: GrowableBuffer::new(device, "Light Buffer", wgpu::BufferUsages::VERTEX),
+        }
+    }
+
+    /**
+     * the light map always has the size of the surface, so this needs to be called whenever the surface is configured with a new size
+     */
+    pub fn resize(&mut self, device: &Device, size: (u32, u32)){
+        self.light_map = create_light_map(device, size);
+        self.composite_bind_group = create_composite_bind_group(device, &self.composite_layout, &self.light_map);
+    }
+
+    pub fn write_lights(&mut self, device: &Device, queue: &Queue, lights: &[LightInstance]){
+        self.light_buffer.write(device, queue, lights);
+    }
+
+    /**
+     * first pass of a frame: clears the light map to the ambient light and adds all lights. Uses the same camera and viewport as the world
+     */
+    pub fn draw_light_map(&self, encoder: &mut CommandEncoder, quad_buffer: &wgpu::Buffer, camera_bind_group: &BindGroup, ambient_light: [f32; 4], viewport: [f32; 4]){
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Light Map Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: &self.light_map,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color { r: ambient_light[0] as f64, g: ambient_light[1] as f64, b: ambient_light[2] as f64, a: ambient_light[3] as f64 }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        if self.light_buffer.len() == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.light_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_viewport(viewport[0], viewport[1], viewport[2], viewport[3], 0.0, 1.0);
+        render_pass.set_vertex_buffer(0, quad_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.light_buffer.slice());
+        render_pass.draw(0..6, 0..(self.light_buffer.len() as u32));
+    }
+
+    /**
+     * multiplies the light map onto everything that was drawn so far in the render pass, within the current viewport
+     */
+    pub fn composite<'a>(&'a self, render_pass: &mut RenderPass<'a>){
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+
+fn create_light_map(device: &Device, size: (u32, u32)) -> TextureView{
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Light Map"),
+        size: wgpu::Extent3d { width: size.0.max(1), height: size.1.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: LIGHT_MAP_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_composite_bind_group(device: &Device, layout: &BindGroupLayout, light_map: &TextureView) -> BindGroup{
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(light_map),
+            },
+        ],
+        label: Some("light_map_bind_group"),
+    })
+}
@@ -0,0 +1,32 @@
+// bright parts of the image bleed into their surroundings. This is a single pass approximation, it samples rings around every pixel
+// instead of blurring a downscaled copy, which is good enough for a few torches and sparks
+
+let THRESHOLD: f32 = 0.6;
+let INTENSITY: f32 = 0.6;
+let SAMPLES_PER_RING: i32 = 12;
+
+fn bright_part(uv: vec2<f32>) -> vec3<f32> {
+    let color = textureSampleLevel(source, source_sampler, uv, 0.0).rgb;
+    return max(color - vec3<f32>(THRESHOLD), vec3<f32>(0.0));
+}
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(source, source_sampler, in.uv, 0.0);
+    let texel = 1.0 / post.resolution;
+    var bloom = bright_part(in.uv);
+    var weight_sum = 1.0;
+    for (var ring = 1; ring <= 3; ring = ring + 1) {
+        // the rings get further apart and count less
+        let radius = f32(ring * ring) * 4.0;
+        let weight = 1.0 / f32(ring + 1);
+        for (var i = 0; i < SAMPLES_PER_RING; i = i + 1) {
+            // every ring is rotated a bit, so the samples do not line up
+            let angle = (f32(i) + f32(ring) * 0.5) * 6.2831853 / f32(SAMPLES_PER_RING);
+            let offset = vec2<f32>(cos(angle), sin(angle)) * radius * texel;
+            bloom = bloom + bright_part(in.uv + offset) * weight;
+            weight_sum = weight_sum + weight;
+        }
+    }
+    return vec4<f32>(color.rgb + bloom / weight_sum * INTENSITY * 4.0, color.a);
+}
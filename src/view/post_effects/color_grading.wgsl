@@ -0,0 +1,16 @@
+// a bit less saturated, a bit more contrast and slightly warm, the look of a mine lit by torches
+
+let SATURATION: f32 = 0.85;
+let CONTRAST: f32 = 1.12;
+// the colors are linear here, this is middle grey
+let PIVOT: f32 = 0.18;
+let TINT: vec3<f32> = vec3<f32>(1.05, 1.0, 0.92);
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    var graded = mix(vec3<f32>(luminance(color.rgb)), color.rgb, SATURATION);
+    graded = (graded - PIVOT) * CONTRAST + PIVOT;
+    graded = max(graded * TINT, vec3<f32>(0.0));
+    return vec4<f32>(graded, color.a);
+}
@@ -0,0 +1,29 @@
+// simulates color vision deficiencies, to check if the game can still be played with them
+// the matrices are from Machado, Oliveira and Fernandes (2009) for the full severity, they work on linear rgb
+// every row of the matrix is one output channel, vector * matrix with the rows as columns does exactly that
+
+fn simulate(color: vec4<f32>, red: vec3<f32>, green: vec3<f32>, blue: vec3<f32>) -> vec4<f32> {
+    let simulated = color.rgb * mat3x3<f32>(red, green, blue);
+    return vec4<f32>(clamp(simulated, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
+
+// no red cones
+@fragment
+fn fs_protanopia(in: PostOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    return simulate(color, vec3<f32>(0.152286, 1.052583, -0.204868), vec3<f32>(0.114503, 0.786281, 0.099216), vec3<f32>(-0.003882, -0.048116, 1.051998));
+}
+
+// no green cones, the most common one
+@fragment
+fn fs_deuteranopia(in: PostOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    return simulate(color, vec3<f32>(0.367322, 0.860646, -0.227968), vec3<f32>(0.280085, 0.672501, 0.047413), vec3<f32>(-0.011820, 0.042940, 0.968881));
+}
+
+// no blue cones
+@fragment
+fn fs_tritanopia(in: PostOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    return simulate(color, vec3<f32>(1.255528, -0.076749, -0.178779), vec3<f32>(-0.078411, 0.930809, 0.147602), vec3<f32>(0.004733, 0.691367, 0.303900));
+}
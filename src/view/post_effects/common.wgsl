@@ -0,0 +1,40 @@
+// Shared by all post processing passes, the effect itself is appended to this (see PostEffect::source)
+// Every pass reads the previous image from source and draws one triangle over the whole target
+
+// has to match PostUniform in post_processing.rs
+struct PostUniform {
+    // size of the target in pixels
+    resolution: vec2<f32>,
+    // seconds since the renderer started
+    time: f32,
+    padding: f32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+struct PostOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // texture coordinates of the source, 0/0 is the top left corner
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> PostOutput {
+    var out: PostOutput;
+    let x = f32(i32(vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(vertex_index >> 1u) * 4 - 1);
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    // clip space y points up, texture coordinates point down
+    out.uv = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+    return out;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
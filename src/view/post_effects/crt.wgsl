@@ -0,0 +1,29 @@
+// an old monitor: curved screen, scanlines, colors that are slightly out of alignment and a faint flicker
+
+let CURVATURE: f32 = 0.08;
+let SCANLINE_STRENGTH: f32 = 0.25;
+let ABERRATION: f32 = 1.5;
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    // bend the coordinates outwards, the further from the center the stronger
+    let centered = in.uv * 2.0 - 1.0;
+    let bent = centered * (1.0 + CURVATURE * dot(centered, centered));
+    let uv = bent * 0.5 + 0.5;
+    let outside = any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0));
+
+    // red and blue are sampled a few pixels apart from green
+    let shift = vec2<f32>(ABERRATION / post.resolution.x, 0.0);
+    let red = textureSampleLevel(source, source_sampler, uv + shift, 0.0).r;
+    let green_alpha = textureSampleLevel(source, source_sampler, uv, 0.0);
+    let blue = textureSampleLevel(source, source_sampler, uv - shift, 0.0).b;
+    var color = vec3<f32>(red, green_alpha.g, blue);
+
+    let scanline = 0.5 + 0.5 * sin(uv.y * post.resolution.y * 3.14159265);
+    color = color * (1.0 - SCANLINE_STRENGTH * scanline);
+    color = color * (0.97 + 0.03 * sin(post.time * 60.0));
+    if (outside) {
+        color = vec3<f32>(0.0);
+    }
+    return vec4<f32>(color, green_alpha.a);
+}
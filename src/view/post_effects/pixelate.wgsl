@@ -0,0 +1,11 @@
+// shows the image in big blocks of pixels, for a retro look
+
+let BLOCK_SIZE: f32 = 4.0;
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    // every pixel of a block samples the center of the block
+    let block = floor(in.uv * post.resolution / BLOCK_SIZE);
+    let uv = (block + 0.5) * BLOCK_SIZE / post.resolution;
+    return textureSampleLevel(source, source_sampler, uv, 0.0);
+}
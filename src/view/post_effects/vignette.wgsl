@@ -0,0 +1,10 @@
+// darkens the corners, so the view feels like looking through a tunnel
+
+@fragment
+fn fs_main(in: PostOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv);
+    // corrected for the aspect ratio, so the vignette is round and not stretched with the window
+    let centered = (in.uv - 0.5) * vec2<f32>(post.resolution.x / post.resolution.y, 1.0);
+    let darkening = smoothstep(0.45, 1.1, length(centered));
+    return vec4<f32>(color.rgb * (1.0 - darkening * 0.75), color.a);
+}
@@ -0,0 +1,302 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, BindGroupLayout, BindGroupLayoutDescriptor, Buffer, CommandEncoder, Device, Queue, RenderPipeline, Sampler, TextureView, util::DeviceExt};
+
+
+//what F5 - F8 switch if --post-effects is not given. They all start switched off, so the picture only changes if someone asks for it
+pub const TOGGLEABLE_POST_EFFECTS: [PostEffect; 4] = [PostEffect::ColorGrading, PostEffect::Vignette, PostEffect::Bloom, PostEffect::Crt];
+
+//vertex shader, bindings and helpers every effect is appended to
+const COMMON_SOURCE: &str = include_str!("post_effects/common.wgsl");
+
+
+/**
+ * One full screen pass of the post processing chain. Every effect reads the image the previous one produced and writes a new one,
+ * so the order of the chain matters, e.g. a vignette before the crt effect gets bent with the screen
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostEffect{
+    Vignette,
+    ColorGrading,
+    Bloom,
+    Crt,
+    Pixelate,
+    //the three colorblind simulations
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+pub const ALL_POST_EFFECTS: [PostEffect; 8] = [
+    PostEffect::Vignette,
+    PostEffect::ColorGrading,
+    PostEffect::Bloom,
+    PostEffect::Crt,
+    PostEffect::Pixelate,
+    PostEffect::Protanopia,
+    PostEffect::Deuteranopia,
+    PostEffect::Tritanopia,
+];
+
+impl PostEffect {
+    pub fn parse(value: &str) -> Option<Self>{
+        ALL_POST_EFFECTS.iter().copied().find(|effect| effect.name() == value.trim().to_lowercase())
+    }
+
+    /**
+     * comma separated names in the order they should be applied, "none" is an empty chain
+     */
+    pub fn parse_chain(value: &str) -> Result<Vec<Self>, String>{
+        if value.trim().eq_ignore_ascii_case("none") {
+            return Ok(Vec::new());
+        }
+        value.split(',').filter(|name| !name.trim().is_empty()).map(|name| PostEffect::parse(name).ok_or_else(|| name.trim().to_string())).collect()
+    }
+
+    pub fn name(&self) -> &'static str{
+        match self {
+            PostEffect::Vignette => "vignette",
+            PostEffect::ColorGrading => "color-grading",
+            PostEffect::Bloom => "bloom",
+            PostEffect::Crt => "crt",
+            PostEffect::Pixelate => "pixelate",
+            PostEffect::Protanopia => "protanopia",
+            PostEffect::Deuteranopia => "deuteranopia",
+            PostEffect::Tritanopia => "tritanopia",
+        }
+    }
+
+    fn source(&self) -> &'static str{
+        match self {
+            PostEffect::Vignette => include_str!("post_effects/vignette.wgsl"),
+            PostEffect::ColorGrading => include_str!("post_effects/color_grading.wgsl"),
+            PostEffect::Bloom => include_str!("post_effects/bloom.wgsl"),
+            PostEffect::Crt => include_str!("post_effects/crt.wgsl"),
+            PostEffect::Pixelate => include_str!("post_effects/pixelate.wgsl"),
+            PostEffect::Protanopia | PostEffect::Deuteranopia | PostEffect::Tritanopia => include_str!("post_effects/colorblind.wgsl"),
+        }
+    }
+
+    fn entry_point(&self) -> &'static str{
+        match self {
+            PostEffect::Protanopia => "fs_protanopia",
+            PostEffect::Deuteranopia => "fs_deuteranopia",
+            PostEffect::Tritanopia => "fs_tritanopia",
+            _ => "fs_main",
+        }
+    }
+
+    fn index(&self) -> usize{
+        ALL_POST_EFFECTS.iter().position(|effect| effect == self).unwrap()
+    }
+}
+
+
+// The layout has to match PostUniform in post_effects/common.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+struct PostUniform {
+    resolution: [f32; 2],
+    time: f32,
+    padding: f32,
+}
+
+
+/**
+ * Runs the post processing chain. If there are any effects, the scene is drawn into an offscreen target instead of the surface,
+ * the effects then bounce between two offscreen targets and the last one writes into the surface. Screen space instances are drawn afterwards, so the overlay is never distorted
+ * Every effect gets its own pipeline, they are all created up front, so toggling an effect at runtime costs nothing
+ */
+pub struct PostProcessor{
+    pipelines: Vec<RenderPipeline>,
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    uniform_buffer: Buffer,
+    //ping pong targets, the scene is always drawn into the first one
+    targets: [TextureView; 2],
+    //bind_groups[i] reads from targets[i]
+    bind_groups: [BindGroup; 2],
+    size: (u32, u32),
+}
+
+impl PostProcessor {
+
+    pub fn new(device: &Device, surface_format: wgpu::TextureFormat, size: (u32, u32)) -> Self{
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("post_processing_bind_group_layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Processing Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = ALL_POST_EFFECTS.iter().map(|effect| {
+            let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: Some(effect.name()),
+                source: wgpu::ShaderSource::Wgsl(format!("{}{}", COMMON_SOURCE, effect.source()).into()),
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(effect.name()),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: effect.entry_point(),
+                    //every pass replaces the whole image, nothing to blend with
+                    targets: &[wgpu::ColorTargetState {
+                        format: surface_format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState { cull_mode: None, ..Default::default() },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        }).collect();
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Processing Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[PostUniform::default()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let targets = [create_target(device, surface_format, size), create_target(device, surface_format, size)];
+        let bind_groups = [
+            create_bind_group(device, &layout, &targets[0], &sampler, &uniform_buffer),
+            create_bind_group(device, &layout, &targets[1], &sampler, &uniform_buffer),
+        ];
+        PostProcessor { pipelines, layout, sampler, uniform_buffer, targets, bind_groups, size }
+    }
+
+    /**
+     * the targets always have the size of the surface, so this needs to be called whenever the surface is configured with a new size
+     */
+    pub fn resize(&mut self, device: &Device, surface_format: wgpu::TextureFormat, size: (u32, u32)){
+        self.targets = [create_target(device, surface_format, size), create_target(device, surface_format, size)];
+        self.bind_groups = [
+            create_bind_group(device, &self.layout, &self.targets[0], &self.sampler, &self.uniform_buffer),
+            create_bind_group(device, &self.layout, &self.targets[1], &self.sampler, &self.uniform_buffer),
+        ];
+        self.size = size;
+    }
+
+    /**
+     * time is in seconds and only used by animated effects like the flicker of the crt
+     */
+    pub fn write_uniform(&self, queue: &Queue, time: f32){
+        let uniform = PostUniform { resolution: [self.size.0.max(1) as f32, self.size.1.max(1) as f32], time, padding: 0.0 };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /**
+     * where the scene has to be drawn to, so apply can pick it up
+     */
+    pub fn scene_target(&self) -> &TextureView{
+        &self.targets[0]
+    }
+
+    /**
+     * runs all effects in the given order on the scene target, the last one writes into output. Does nothing for an empty chain, in that case the scene should have been drawn into output directly
+     */
+    pub fn apply(&self, encoder: &mut CommandEncoder, effects: &[PostEffect], output: &TextureView){
+        let mut source = 0;
+        for (i, effect) in effects.iter().enumerate() {
+            let target = if i + 1 == effects.len() { output } else { &self.targets[1 - source] };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Processing Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        //every pixel gets overwritten anyway
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.pipelines[effect.index()]);
+            render_pass.set_bind_group(0, &self.bind_groups[source], &[]);
+            render_pass.draw(0..3, 0..1);
+            source = 1 - source;
+        }
+    }
+}
+
+
+fn create_target(device: &Device, format: wgpu::TextureFormat, size: (u32, u32)) -> TextureView{
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Post Processing Target"),
+        size: wgpu::Extent3d { width: size.0.max(1), height: size.1.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        //same format as the surface, so the scene and the effects can be drawn with the same pipelines
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_bind_group(device: &Device, layout: &BindGroupLayout, source: &TextureView, sampler: &Sampler, uniform_buffer: &Buffer) -> BindGroup{
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("post_processing_bind_group"),
+    })
+}
@@ -0,0 +1,63 @@
+use std::{sync::atomic::{AtomicU64, Ordering}, time::Duration};
+
+use super::growable_buffer::BufferStats;
+
+
+//how much a new frame time counts into the smoothed one, lower values make the numbers steadier but slower to react
+const SMOOTHING: f64 = 0.05;
+
+
+/**
+ * Written by whatever renders the frames (wgpu_render or the dummy renderer) and read by the debug overlay on the communication thread.
+ * Floats are stored as their bits in atomics, so no lock is needed on the render thread
+ */
+#[derive(Debug, Default)]
+pub struct RenderStatistics{
+    frames: AtomicU64,
+    //smoothed time between two frames in milliseconds, as f64 bits
+    frame_time_ms: AtomicU64,
+    instance_buffer_capacity: AtomicU64,
+    instance_buffer_reallocations: AtomicU64,
+}
+
+impl RenderStatistics {
+    pub fn new() -> Self{
+        RenderStatistics::default()
+    }
+
+    pub fn record_frame(&self, time_since_last_frame: Duration){
+        let frame_time_ms = time_since_last_frame.as_secs_f64() * 1000.0;
+        let smoothed = match self.frames.fetch_add(1, Ordering::Relaxed) {
+            0 => frame_time_ms,
+            _ => self.frame_time_ms() * (1.0 - SMOOTHING) + frame_time_ms * SMOOTHING,
+        };
+        self.frame_time_ms.store(smoothed.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn record_instance_buffer(&self, stats: BufferStats){
+        self.instance_buffer_capacity.store(stats.capacity, Ordering::Relaxed);
+        self.instance_buffer_reallocations.store(stats.reallocations as u64, Ordering::Relaxed);
+    }
+
+    pub fn frames(&self) -> u64{
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn frame_time_ms(&self) -> f64{
+        f64::from_bits(self.frame_time_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn frames_per_second(&self) -> f64{
+        let frame_time_ms = self.frame_time_ms();
+        if frame_time_ms > 0.0 { 1000.0 / frame_time_ms } else { 0.0 }
+    }
+
+    //in elements, 0 as long as nothing was uploaded (e.g. in --no-GUI mode)
+    pub fn instance_buffer_capacity(&self) -> u64{
+        self.instance_buffer_capacity.load(Ordering::Relaxed)
+    }
+
+    pub fn instance_buffer_reallocations(&self) -> u64{
+        self.instance_buffer_reallocations.load(Ordering::Relaxed)
+    }
+}
@@ -1,18 +1,17 @@
-use std::{sync::{Arc, atomic::AtomicBool}, thread::JoinHandle, time::SystemTime};
+use std::{sync::{Arc, atomic::AtomicBool}, thread::JoinHandle, time::SystemTime, path::Path};
 use bytemuck::{Pod, Zeroable};
 use flume::{Sender, Receiver};
 
 use tokio::runtime::{Handle};
-use wgpu::{ util::DeviceExt};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::{WindowBuilder}, dpi::{Size, PhysicalSize},
 };
 
-use crate::{controller::controller_input::{ControllerInput, MouseInputType}, constants::{WINDOW_INIT_X, WINDOW_INIT_Y}};
+use crate::{controller::controller_input::{ControllerInput, MouseInputType}, constants::{WINDOW_INIT_X, WINDOW_INIT_Y, SPRITE_MANIFEST_PATH}, launch_options::LaunchOptions, signals};
 
-use super::renderer_init::{self};
+use super::{renderer_init::{self, RendererResources}, dummy_renderer, texture_watcher, sprite_manifest::Sprites, frame::{Frame, CameraUniform}, render_statistics::RenderStatistics, growable_buffer::GrowableBuffer, screenshot, lighting::LightInstance};
 
 
     // To create a buffer that will store the shape of our triangle.
@@ -24,6 +23,8 @@ use super::renderer_init::{self};
         pub(crate) position: [f32; 2],
         pub(crate) tex_i: u32,
         pub(crate) tex_coords: [f32; 2],
+        //rgba, the sampled texture color is multiplied with this
+        pub(crate) tint: [f32; 4],
     }
 
 //unsafe impl bytemuck::Pod for Vertex {}   use these for implementing Pod and Zeroable for structs, that cant derive these traits
@@ -31,6 +32,7 @@ use super::renderer_init::{self};
 
 //create a function that returns the descriptor for the vertex, that describes how the vertices are used
 impl Vertex {
+    #[allow(dead_code)]
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,         //defines how wide a vertex is (C: sizeof()), in order to read the next vertex, the shader will read this many bytes further into the buffer
@@ -50,6 +52,11 @@ impl Vertex {
                     offset: (std::mem::size_of::<[f32;2]>() + std::mem::size_of::<u32>()) as wgpu::BufferAddress,
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute{
+                    offset: (std::mem::size_of::<[f32;2]>() * 2 + std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
                 }
             ]
         }
@@ -57,24 +64,203 @@ impl Vertex {
 }
 
 
-pub(crate) async fn wgpu_render( mut threads_vec: Vec<JoinHandle<()>>, running: Arc<AtomicBool>, controller_sender: Sender<ControllerInput>, vertex_receiver: Receiver<Vec<Vertex>>, _rt: Handle) {
-    env_logger::init();
-    let mut ctr_sender = Some(controller_sender);
-    let event_loop = EventLoop::new();
+    // One corner of the unit quad that every instance is drawn with. 0.0/0.0 is the top left corner, 1.0/1.0 the bottom right one
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+    pub struct QuadVertex {
+        pub(crate) corner: [f32; 2],
+    }
+
+//the two triangles of the quad, in the same order construct_vertices used to emit them: top right, top left, bottom left, top right, bottom left, bottom right
+//in wgpu, the vertex faces need to be specified in counter-clockwise order (after the y flip in the shader)
+pub const UNIT_QUAD: [QuadVertex; 6] = [
+    QuadVertex{ corner: [1.0, 0.0] },
+    QuadVertex{ corner: [0.0, 0.0] },
+    QuadVertex{ corner: [0.0, 1.0] },
+    QuadVertex{ corner: [1.0, 0.0] },
+    QuadVertex{ corner: [0.0, 1.0] },
+    QuadVertex{ corner: [1.0, 1.0] },
+];
+
+impl QuadVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<QuadVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ]
+        }
+    }
+}
+
+
+    // Everything that is needed to draw one object. These are put into a second vertex buffer, which advances once per instance instead of once per vertex
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+    pub struct Instance {
+        //where the pivot of the object is in world space
+        pub(crate) position: [f32; 2],
+        //half of the width and height, just like DrawableObject::get_size
+        pub(crate) size: [f32; 2],
+        pub(crate) tex_i: u32,
+        //[left, top, right, bottom] in texture coordinates
+        pub(crate) uv_rect: [f32; 4],
+        //rgba, the sampled texture color is multiplied with this. White (all 1.0) keeps the texture as it is
+        pub(crate) tint: [f32; 4],
+        //in radians, clockwise on the screen, around the pivot
+        pub(crate) rotation: f32,
+        //the point of the quad that ends up at position, 0/0 is the top left corner and 1/1 the bottom right one, see DrawableObject::get_pivot
+        pub(crate) pivot: [f32; 2],
+    }
 
-    let window = WindowBuilder::new().with_title("Driven UnderGround!").with_visible(false).with_min_inner_size(Size::Physical(PhysicalSize{width: WINDOW_INIT_X, height: WINDOW_INIT_Y})).build(&event_loop).unwrap();
+impl Instance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,                                  //the shader only moves on to the next instance after all vertices of the quad are processed
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() * 2) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() * 2 + std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() * 2 + std::mem::size_of::<u32>() + std::mem::size_of::<[f32; 4]>()) as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() * 2 + std::mem::size_of::<u32>() + std::mem::size_of::<[f32; 4]>() * 2) as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() * 2 + std::mem::size_of::<u32>() + std::mem::size_of::<[f32; 4]>() * 2 + std::mem::size_of::<f32>()) as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ]
+        }
+    }
+
+    /**
+     * the same calculation the vertex shader does, for everything that needs the actual triangles on the cpu side. The vertices are in world space
+     */
+    #[allow(dead_code)]
+    pub fn to_vertices(&self) -> [Vertex; 6]{
+        let (sin, cos) = self.rotation.sin_cos();
+        UNIT_QUAD.map(|quad_vertex| {
+            let corner = quad_vertex.corner;
+            let local = [(corner[0] - self.pivot[0]) * 2.0 * self.size[0], (corner[1] - self.pivot[1]) * 2.0 * self.size[1]];
+            Vertex{
+                position: [self.position[0] + local[0] * cos - local[1] * sin, self.position[1] + local[0] * sin + local[1] * cos],
+                tex_i: self.tex_i,
+                tex_coords: [self.uv_rect[0] + (self.uv_rect[2] - self.uv_rect[0]) * corner[0], self.uv_rect[1] + (self.uv_rect[3] - self.uv_rect[1]) * corner[1]],
+                tint: self.tint,
+            }
+        })
+    }
+}
+
+
+pub(crate) async fn wgpu_render( mut threads_vec: Vec<JoinHandle<()>>, running: Arc<AtomicBool>, controller_sender: Sender<ControllerInput>, vertex_receiver: Receiver<Frame>, sprites: Arc<Sprites>, render_statistics: Arc<RenderStatistics>, options: LaunchOptions, rt: Handle) {
+    env_logger::init();
+    //winit panics if there is no display to connect to (e.g. over ssh or in a container), that is just another reason to run without a window
+    let event_loop = match std::panic::catch_unwind(EventLoop::new) {
+        Ok(event_loop) => event_loop,
+        Err(_) => {
+            eprintln!("Could not connect to a display, continuing without a window, as if --no-GUI was given");
+            return dummy_renderer::go(threads_vec, running, controller_sender, vertex_receiver, sprites, render_statistics, options, rt).await;
+        }
+    };
+
+    let window = match WindowBuilder::new().with_title("Driven UnderGround!").with_visible(false).with_min_inner_size(Size::Physical(PhysicalSize{width: WINDOW_INIT_X, height: WINDOW_INIT_Y})).build(&event_loop) {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("Could not create a window: {}", e);
+            eprintln!("Continuing without a window, as if --no-GUI was given");
+            return dummy_renderer::go(threads_vec, running, controller_sender, vertex_receiver, sprites, render_statistics, options, rt).await;
+        }
+    };
     
-    let (            
+    let RendererResources {
         surface,
         device,
         queue,
         mut config,
         mut size,
-        bkcolor,
+        background_color: bkcolor,
         render_pipeline,
-        diffuse_bind_group,
-    ) = renderer_init::new(&window);
+        mut sprite_bindings,
+        camera_buffer,
+        camera_bind_group,
+        screen_camera_buffer,
+        screen_camera_bind_group,
+        quad_buffer,
+        mut light_renderer,
+        mut post_processor,
+    } = match renderer_init::new(&window, &sprites, &options) {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            eprintln!("Could not initialize the renderer: {}", e);
+            eprintln!("Continuing without a window, as if --no-GUI was given. Try another --backend or --fallback-adapter");
+            drop(window);
+            return dummy_renderer::go(threads_vec, running, controller_sender, vertex_receiver, sprites, render_statistics, options, rt).await;
+        }
+    };
+    let texture_reloads = if options.hot_reload {
+        let (watcher_thread, texture_reloads) = texture_watcher::spawn(Path::new(SPRITE_MANIFEST_PATH), &sprites, running.clone());
+        //joined with the others when the window is closed
+        threads_vec.push(watcher_thread);
+        Some(texture_reloads)
+    } else {
+        None
+    };
+    let mut ctr_sender = Some(controller_sender);
     let mut last_render= SystemTime::now();
+    //animated post effects run on this clock
+    let started = SystemTime::now();
+    //the instances only change if the model changed, in that case the communication loop sends a new Arc. Otherwise the buffer from the last frame is drawn again without uploading anything
+    let mut instance_buffer: GrowableBuffer<Instance> = GrowableBuffer::new(&device, "Instance Buffer", wgpu::BufferUsages::VERTEX);
+    let mut uploaded_instances: Option<Arc<Vec<Instance>>> = None;
+    let mut screen_instance_buffer: GrowableBuffer<Instance> = GrowableBuffer::new(&device, "Screen Instance Buffer", wgpu::BufferUsages::VERTEX);
+    let mut uploaded_screen_instances: Option<Arc<Vec<Instance>>> = None;
+    let mut uploaded_lights: Option<Arc<Vec<LightInstance>>> = None;
+    let mut particle_buffer: GrowableBuffer<Instance> = GrowableBuffer::new(&device, "Particle Instance Buffer", wgpu::BufferUsages::VERTEX);
+    let mut uploaded_particles: Option<Arc<Vec<Instance>>> = None;
+    if options.input_script.is_some() {
+        println!("--script is only played by the headless runner (--no-GUI), ignoring it");
+    }
+    if options.record.is_some() {
+        println!("--record only works with the headless runner (--no-GUI), ignoring it");
+    }
+    //SIGINT / SIGTERM close the window like the close button does, the shutdown has to happen on the event loop, since that owns the controller sender
+    let signal_proxy = event_loop.create_proxy();
+    rt.spawn(async move {
+        signals::termination_signal().await;
+        println!("Got a termination signal, shutting down");
+        let _ = signal_proxy.send_event(());
+    });
     window.set_visible(true);
     event_loop.run(move |event, _, control_flow| match event {
         Event::RedrawRequested(window_id) if window_id == window.id() => {
@@ -91,33 +277,30 @@ pub(crate) async fn wgpu_render( mut threads_vec: Vec<JoinHandle<()>>, running:
                     config.width = size.width;
                     config.height = size.height;
                     surface.configure(&device, &config);
+                    light_renderer.resize(&device, (config.width, config.height));
+                    post_processor.resize(&device, config.format, (config.width, config.height));
                     if let Some(ref controller_sender) = ctr_sender{
                         controller_sender.send(ControllerInput::WindowResized { dimensions: (physical_size.width , physical_size.height )  }).expect("Could not send window resized info to the controller");
                     }
                 }
             }
-            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            WindowEvent::ScaleFactorChanged { new_inner_size, scale_factor } => {
                 // new_inner_size is &&mut so we have to dereference it twice
                 if new_inner_size.width > 0 && new_inner_size.height > 0 {
                     size = **new_inner_size;
                     config.width = size.width;
                     config.height = size.height;
                     surface.configure(&device, &config);
+                    light_renderer.resize(&device, (config.width, config.height));
+                    post_processor.resize(&device, config.format, (config.width, config.height));
+                    if let Some(ref controller_sender) = ctr_sender{
+                        controller_sender.send(ControllerInput::ScaleFactorChanged { scale_factor: *scale_factor, dimensions: (size.width, size.height) }).expect("Could not send scale factor change to the controller");
+                    }
                 }
             }
             WindowEvent::CloseRequested
              => {
-                running.store(false, std::sync::atomic::Ordering::SeqCst);
-                //dropping the sender will result in an Err Result on the controller thread recv() method
-                ctr_sender = None;
-                    (vertex_receiver).recv().unwrap();   //make the sender thread complete one more loop, in order to make it realize the running bool was set to false
-                
-                while let Some(cur_thread) = threads_vec.pop() {
-                    cur_thread.join().unwrap();
-                }
-                println!("Gracefully exiting ...");
-                *control_flow = ControlFlow::Exit;
-
+                close(&mut threads_vec, &running, &mut ctr_sender, &vertex_receiver, control_flow);
             }
            WindowEvent::KeyboardInput { device_id: _ , input, is_synthetic: _ }
              => {
@@ -174,20 +357,115 @@ pub(crate) async fn wgpu_render( mut threads_vec: Vec<JoinHandle<()>>, running:
         //16.6ms are needed for 60fps (that is 16666 qs)
         let now = SystemTime::now();
 
-        let _time_passed_in_ms = last_render.elapsed().unwrap().as_micros();
-
-        //println!("qs passed since last rendering: {}", time_passed_in_ms);
+        //shown in the debug overlay (F3)
+        render_statistics.record_frame(last_render.elapsed().unwrap_or_default());
         last_render = now;
 
-        let vertices: Vec<Vertex> = vertex_receiver.recv().unwrap();
+        //the communication loop is gone once the window was closed, there is nothing left to draw
+        let frame: Frame = match vertex_receiver.recv() {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
 
-        let vertex_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
+        if let Some(texture_reloads) = &texture_reloads {
+            for reload in texture_reloads.try_iter() {
+                sprite_bindings.replace(reload.tex_i, &reload.sprite, &device, &queue);
+            }
+        }
 
+        queue.write_buffer(&camera_buffer, 0, bytemuck::cast_slice(&[frame.camera]));
+        let is_unchanged = matches!(&uploaded_instances, Some(instances) if Arc::ptr_eq(instances, &frame.instances));
+        if !is_unchanged {
+            instance_buffer.write(&device, &queue, &frame.instances);
+            uploaded_instances = Some(frame.instances.clone());
+            render_statistics.record_instance_buffer(instance_buffer.stats());
+        }
+        let is_unchanged = matches!(&uploaded_particles, Some(particles) if Arc::ptr_eq(particles, &frame.particles));
+        if !is_unchanged {
+            particle_buffer.write(&device, &queue, &frame.particles);
+            uploaded_particles = Some(frame.particles.clone());
+        }
+        let is_unchanged = matches!(&uploaded_lights, Some(lights) if Arc::ptr_eq(lights, &frame.lights));
+        if !is_unchanged {
+            light_renderer.write_lights(&device, &queue, &frame.lights);
+            uploaded_lights = Some(frame.lights.clone());
+        }
+        //screen instances are placed in logical pixels, so text keeps its size on high dpi screens
+        let scale_factor = window.scale_factor();
+        queue.write_buffer(&screen_camera_buffer, 0, bytemuck::cast_slice(&[CameraUniform::screen_space((config.width as f64 / scale_factor) as u32, (config.height as f64 / scale_factor) as u32)]));
+        let is_unchanged = matches!(&uploaded_screen_instances, Some(instances) if Arc::ptr_eq(instances, &frame.screen_instances));
+        if !is_unchanged {
+            screen_instance_buffer.write(&device, &queue, &frame.screen_instances);
+            uploaded_screen_instances = Some(frame.screen_instances.clone());
+        }
+        post_processor.write_uniform(&queue, started.elapsed().unwrap_or_default().as_secs_f32());
+
+
+        //records everything that is needed to draw the frame into the given view, this is used for the surface and for screenshots
+        let draw_frame = |encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView| {
+            let viewport = frame.viewport;
+            light_renderer.draw_light_map(encoder, &quad_buffer, &camera_bind_group, frame.ambient_light, [viewport.x, viewport.y, viewport.width, viewport.height]);
+            //with post effects, the world goes into an offscreen target first and the effects write it into the view
+            let scene_view = if frame.post_effects.is_empty() { view } else { post_processor.scene_target() };
+            //a render pass is a part of a program in which the given view is drawn to.
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {          //color attachments describe where we are going to draw to
+                    view: scene_view,                                           //created view as target, to render to the screen, this generally is the texture destination of the colors
+                    resolve_target: None,                                       //texture that will receive the resolved output, this is the same as view unless multisampling is enabled
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(bkcolor),
+                        store: true,
+                    },
+
+                }],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&render_pipeline); // 2.
+            render_pass.set_bind_group(0, sprite_bindings.bind_group(), &[]);   
+            render_pass.set_bind_group(1, &camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, quad_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, instance_buffer.slice());
+            //the world only covers the viewport (smaller than the window if it is letterboxed), the rest keeps the clear color
+            render_pass.set_viewport(viewport.x, viewport.y, viewport.width, viewport.height, 0.0, 1.0);
+            //the particles go in between the instances, right after the last one in RenderLayer::Effects
+            let effects_end = (frame.effects_end as u32).min(instance_buffer.len() as u32);
+            render_pass.draw(0..(UNIT_QUAD.len() as u32), 0..effects_end);
+            render_pass.set_vertex_buffer(1, particle_buffer.slice());
+            render_pass.draw(0..(UNIT_QUAD.len() as u32), 0..(particle_buffer.len() as u32));
+            render_pass.set_vertex_buffer(1, instance_buffer.slice());
+            render_pass.draw(0..(UNIT_QUAD.len() as u32), effects_end..(instance_buffer.len() as u32));
+            //darkens the world where there is no light, the screen instances after this are not affected
+            light_renderer.composite(&mut render_pass);
+            drop(render_pass);
+
+            post_processor.apply(encoder, &frame.post_effects, view);
+
+            //everything in screen space ends up on top of the world, in its own pass so the post effects do not touch the overlay
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screen Space Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&render_pipeline);
+            render_pass.set_bind_group(0, sprite_bindings.bind_group(), &[]);
+            render_pass.set_vertex_buffer(0, quad_buffer.slice(..));
+            render_pass.set_bind_group(1, &screen_camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(1, screen_instance_buffer.slice());
+            render_pass.draw(0..(UNIT_QUAD.len() as u32), 0..(screen_instance_buffer.len() as u32));
+            //the render pass borrows the encoder mutably until it is dropped at the end of this closure
+        };
+
+        if frame.screenshot {
+            screenshot::capture(&device, &queue, &config, draw_frame);
+        }
 
         //get the frame to render to
         let output = surface.get_current_texture();
@@ -203,27 +481,7 @@ pub(crate) async fn wgpu_render( mut threads_vec: Vec<JoinHandle<()>>, running:
             label: Some("Render Encoder"),
         });
 
-        //a render pass is a part of a program in which the given view is drawn to.
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[wgpu::RenderPassColorAttachment {          //color attachments describe where we are going to draw to
-                view: &view,                                                //created view as target, to render to the screen, this generally is the texture destination of the colors
-                resolve_target: None,                                       //texture that will receive the resolved output, this is the same as view unless multisampling is enabled
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(bkcolor),
-                    store: true,
-                },
-
-            }],
-            depth_stencil_attachment: None,
-        });
-        render_pass.set_pipeline(&render_pipeline); // 2.
-        render_pass.set_bind_group(0, &diffuse_bind_group, &[]);   
-        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-        render_pass.draw(0..(vertices.len() as u32), 0..1);
-
-        drop(render_pass);                     //this is needed, because in the previous step, the _render_pass object borrowed encoder mutably,
-                                                //  and thus we need to drop that borrow in order to use the encoder in the next step
+        draw_frame(&mut encoder, &view);
 
             // submit will accept anything that implements IntoIter
         queue.submit(std::iter::once(encoder.finish()));
@@ -250,6 +508,24 @@ pub(crate) async fn wgpu_render( mut threads_vec: Vec<JoinHandle<()>>, running:
             Err(e) => eprintln!("{:?}", e),
         }
     }
+        //only sent by the signal handler above
+        Event::UserEvent(()) => close(&mut threads_vec, &running, &mut ctr_sender, &vertex_receiver, control_flow),
         _ => {}
     });
+}
+
+/**
+ * shuts down the other threads when the window gets closed or the process got a termination signal. Exits with 1 right away if that does not work out
+ */
+fn close(threads_vec: &mut Vec<JoinHandle<()>>, running: &AtomicBool, ctr_sender: &mut Option<Sender<ControllerInput>>, vertex_receiver: &Receiver<Frame>, control_flow: &mut ControlFlow){
+    //already closed, e.g. a signal arrived right after the close button was pressed
+    let controller_sender = match ctr_sender.take() {
+        Some(sender) => sender,
+        None => return,
+    };
+    //dropping the sender will result in an Err Result on the controller thread recv() method
+    if !dummy_renderer::shutdown(threads_vec, running, controller_sender, vertex_receiver) {
+        std::process::exit(1);
+    }
+    *control_flow = ControlFlow::Exit;
 }
\ No newline at end of file
@@ -1,51 +1,144 @@
 
 
-use wgpu::{include_wgsl, SurfaceConfiguration, Surface, Device, RenderPipeline, BindGroup, Queue};
+use std::fmt;
+
+use wgpu::{include_wgsl, SurfaceConfiguration, Surface, Device, RenderPipeline, BindGroup, Queue, Buffer, util::DeviceExt, AdapterInfo, Backends, Features};
 use winit::{window::Window, dpi::PhysicalSize};
 
-use super::{sprite_loading};
+use crate::{constants::BACKGROUND_COLOR, launch_options::LaunchOptions};
+
+use super::{sprite_loading::{self, SpriteBindings}, sprite_manifest::Sprites, frame::CameraUniform, renderer::UNIT_QUAD, lighting::LightRenderer, post_processing::PostProcessor};
+
+
+//the sprites are drawn from a binding array of textures, indexed per instance. Adapters without these can't run the renderer at all
+const REQUIRED_FEATURES: Features = Features::from_bits_truncate(
+    Features::ADDRESS_MODE_CLAMP_TO_BORDER.bits() | /* <-- this is a bitwise operator, not a logical OR */ Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING.bits()
+    | Features::TEXTURE_BINDING_ARRAY.bits());
+
+
+/**
+ * everything renderer_init::new sets up, the render loop takes it apart again
+ */
+pub struct RendererResources{
+    pub surface: Surface,
+    pub device: Device,
+    pub queue: Queue,
+    pub config: SurfaceConfiguration,
+    pub size: PhysicalSize<u32>,
+    pub background_color: wgpu::Color,
+    pub render_pipeline: RenderPipeline,
+    pub sprite_bindings: SpriteBindings,
+    pub camera_buffer: Buffer,
+    pub camera_bind_group: BindGroup,
+    //the camera for screen space instances, see CameraUniform::screen_space
+    pub screen_camera_buffer: Buffer,
+    pub screen_camera_bind_group: BindGroup,
+    pub quad_buffer: Buffer,
+    pub light_renderer: LightRenderer,
+    pub post_processor: PostProcessor,
+}
+
+
+/**
+ * Everything that can go wrong while setting up wgpu. All of these mean that no window can be drawn, so the caller falls back to the --no-GUI mode.
+ * The adapters of all backends are listed, so it is easy to see which --backend would have worked
+ */
+#[derive(Debug)]
+pub enum RendererInitError{
+    NoAdapter{ backends: Backends, force_fallback_adapter: bool, available: Vec<AdapterInfo> },
+    MissingFeatures{ adapter: AdapterInfo, missing: Features, available: Vec<AdapterInfo> },
+    RequestDevice{ adapter: AdapterInfo, source: wgpu::RequestDeviceError },
+    NoSurfaceFormat{ adapter: AdapterInfo },
+}
+
+impl fmt::Display for RendererInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererInitError::NoAdapter { backends, force_fallback_adapter, available } => {
+                write!(f, "no adapter found for backends {:?}{}", backends, if *force_fallback_adapter { " (only fallback adapters were allowed)" } else { "" })?;
+                write_adapters(f, available)
+            },
+            RendererInitError::MissingFeatures { adapter, missing, available } => {
+                write!(f, "adapter \"{}\" ({:?}) is missing the features {:?}", adapter.name, adapter.backend, missing)?;
+                write_adapters(f, available)
+            },
+            RendererInitError::RequestDevice { adapter, source } => write!(f, "could not create a device on adapter \"{}\" ({:?}): {}", adapter.name, adapter.backend, source),
+            RendererInitError::NoSurfaceFormat { adapter } => write!(f, "adapter \"{}\" ({:?}) can't draw to the window surface", adapter.name, adapter.backend),
+        }
+    }
+}
+
+impl std::error::Error for RendererInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RendererInitError::RequestDevice { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+fn write_adapters(f: &mut fmt::Formatter<'_>, adapters: &[AdapterInfo]) -> fmt::Result{
+    if adapters.is_empty() {
+        return write!(f, "\nthere are no adapters on any backend");
+    }
+    write!(f, "\navailable adapters:")?;
+    for adapter in adapters {
+        write!(f, "\n  {} ({:?}, {:?})", adapter.name, adapter.backend, adapter.device_type)?;
+    }
+    Ok(())
+}
+
+//every adapter on every backend, not only the ones that were allowed by the launch options
+fn available_adapters() -> Vec<AdapterInfo>{
+    wgpu::Instance::new(Backends::all()).enumerate_adapters(Backends::all()).map(|adapter| adapter.get_info()).collect()
+}
 
 
 
 
     // Creating some of the wgpu types requires async code
     // in order to use these, the new function needs to be async und thus the whole rendering function, but since it does not return anything, we need pollster in main to block and wait
-    pub fn new(window: &Window) -> (Surface, Device, Queue, SurfaceConfiguration, PhysicalSize<u32>, wgpu::Color, RenderPipeline, BindGroup) {
+    pub fn new(window: &Window, sprites: &Sprites, options: &LaunchOptions) -> Result<RendererResources, RendererInitError> {
 
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
-        // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU
-        let instance = wgpu::Instance::new(wgpu::Backends::VULKAN);
-        println!("Got to here, at least!");
+        // Backends::all => Vulkan + Metal + DX12 + Browser WebGPU, this can be chosen with --backend
+        let backends = options.backend.backends();
+        let instance = wgpu::Instance::new(backends);
 
         let surface = unsafe { instance.create_surface(window) };
 
-        println!("Got to here, at least!");
-
         let adapter = pollster::block_on(instance.request_adapter(
             &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
+                power_preference: options.power_preference(),
                 compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
+                force_fallback_adapter: options.force_fallback_adapter,
             },
-        )).unwrap();   
+        )).ok_or_else(|| RendererInitError::NoAdapter { backends, force_fallback_adapter: options.force_fallback_adapter, available: available_adapters() })?;
+        let adapter_info = adapter.get_info();
+        println!("Using adapter \"{}\" ({:?})", adapter_info.name, adapter_info.backend);
+
+        //you can get a list of supported features by calling adapter.features() or device.features()
+        let missing = REQUIRED_FEATURES - adapter.features();
+        if !missing.is_empty() {
+            return Err(RendererInitError::MissingFeatures { adapter: adapter_info, missing, available: available_adapters() });
+        }
         let (device, queue) = pollster::block_on(adapter.request_device(
             &wgpu::DeviceDescriptor {
-                features: (wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER | /* <-- this is a bitwise operator, not a logical OR */ wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
-                | wgpu::Features::TEXTURE_BINDING_ARRAY),            //you can get a list of supported features by calling adapter.features() or device.features()
+                features: REQUIRED_FEATURES,
 
                 limits: wgpu::Limits::default(),
                 
                 label: None,
             },
             None, // Trace path
-        )).unwrap();
-
+        )).map_err(|source| RendererInitError::RequestDevice { adapter: adapter_info.clone(), source })?;
 
+        let format = surface.get_preferred_format(&adapter).ok_or_else(|| RendererInitError::NoSurfaceFormat { adapter: adapter_info.clone() })?;
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_preferred_format(&adapter).unwrap(),
+            format,
             width: size.width,                      //should not be 0, otherwise it might crash
             height: size.height,                    //should not be 0, otherwise it might crash
             present_mode: wgpu::PresentMode::Fifo,           //Fifo corresponds to V-Sync, waiting for refresh, Mailbox will stop visible tearing, but impact performance slightly, immediate fastest, but with some tearing
@@ -54,8 +147,67 @@ use super::{sprite_loading};
 
         let shader = device.create_shader_module(&include_wgsl!("shader.wgsl"));       //here, we could also put the contents of shader.wgsl as a String into the program, but loading it from a file is more convenient. Make sure to have WGSL extension installed if you want to edit the shader.wgsl file
 
-        //the i value is supposed to be for which part of the game is loaded -> e.g: not all images need to be loaded, switch them out when loading another level
-        let (render_pipeline, diffuse_bind_group) = sprite_loading::load_sprites(0, &device, &queue, &shader, &config);
+        //the camera is the only thing that changes every frame, so it is kept in a small uniform buffer that gets overwritten before each render pass
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::default()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("camera_bind_group_layout"),
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("camera_bind_group"),
+        });
+        //screen space instances (text, ...) are drawn with the same pipeline, only with a camera that maps pixels to the window, see CameraUniform::screen_space
+        let screen_camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Screen Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::screen_space(config.width, config.height)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let screen_camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: screen_camera_buffer.as_entire_binding(),
+                }
+            ],
+            label: Some("screen_camera_bind_group"),
+        });
+
+        //every object is drawn as an instance of this quad, so it never changes
+        let quad_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Unit Quad Buffer"),
+            contents: bytemuck::cast_slice(&UNIT_QUAD),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        //the sprites are decoded at startup from the sprite manifest, here they only get uploaded to the gpu
+        let (render_pipeline, sprite_bindings) = sprite_loading::load_sprites(sprites, &device, &queue, &shader, &config, &camera_bind_group_layout);
+
+        //the light map is drawn with the world camera, so it shares its bind group layout
+        let light_renderer = LightRenderer::new(&device, config.format, (config.width, config.height), &camera_bind_group_layout);
+        let post_processor = PostProcessor::new(&device, config.format, (config.width, config.height));
 
 
 
@@ -72,21 +224,28 @@ use super::{sprite_loading};
 
 
             
-        (
+        Ok(RendererResources {
             surface,
             device,
             queue,
             config,
             size,
-            wgpu::Color {            
-                r: 0.2,
-                g: 0.2,
-                b: 0.2,
-                a: 1.0,
+            background_color: wgpu::Color {            
+                r: BACKGROUND_COLOR[0],
+                g: BACKGROUND_COLOR[1],
+                b: BACKGROUND_COLOR[2],
+                a: BACKGROUND_COLOR[3],
             },
             render_pipeline,
-            diffuse_bind_group,
-        )
+            sprite_bindings,
+            camera_buffer,
+            camera_bind_group,
+            screen_camera_buffer,
+            screen_camera_bind_group,
+            quad_buffer,
+            light_renderer,
+            post_processor,
+        })
     }
      
 
@@ -0,0 +1,110 @@
+use std::{fs, num::NonZeroU32, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use image::RgbaImage;
+use wgpu::{Device, Queue, SurfaceConfiguration, CommandEncoder, TextureView, TextureFormat};
+
+use crate::constants::SCREENSHOT_DIRECTORY;
+
+
+/**
+ * Renders one frame into an offscreen texture instead of the surface, reads it back and writes it as a png into SCREENSHOT_DIRECTORY
+ * The surface texture can't be copied from, so draw_frame is called a second time with the offscreen texture as target.
+ * Encoding the png is done on another thread, so the render loop only has to wait for the gpu
+ */
+pub fn capture(device: &Device, queue: &Queue, config: &SurfaceConfiguration, draw_frame: impl FnOnce(&mut CommandEncoder, &TextureView)){
+    let texture_size = wgpu::Extent3d {
+        width: config.width,
+        height: config.height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Screenshot Texture"),
+        size: texture_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        //same format as the surface, so the render pipeline can draw into it
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    //rows in a texture -> buffer copy need to be aligned to 256 bytes, so there might be some padding at the end of every row
+    let unpadded_bytes_per_row = 4 * config.width;
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = (unpadded_bytes_per_row + alignment - 1) / alignment * alignment;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Screenshot Buffer"),
+        size: (padded_bytes_per_row * config.height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Screenshot Encoder"),
+    });
+    draw_frame(&mut encoder, &view);
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: NonZeroU32::new(config.height),
+            },
+        },
+        texture_size,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let buffer_slice = buffer.slice(..);
+    let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+    //block until the gpu is done, a screenshot is rare enough that the hiccup is acceptable
+    device.poll(wgpu::Maintain::Wait);
+    if let Err(e) = pollster::block_on(mapping) {
+        println!("Could not read back the screenshot from the gpu: {:?}", e);
+        return;
+    }
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * config.height) as usize);
+    {
+        let mapped = buffer_slice.get_mapped_range();
+        for row in mapped.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    buffer.unmap();
+
+    let is_bgra = matches!(config.format, TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb);
+    let (width, height) = (config.width, config.height);
+    std::thread::spawn(move || {
+        if is_bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        let image = match RgbaImage::from_raw(width, height, pixels) {
+            Some(image) => image,
+            None => {
+                println!("Screenshot data does not match the window size, skipping it");
+                return;
+            }
+        };
+        let path = screenshot_path();
+        if let Err(e) = fs::create_dir_all(SCREENSHOT_DIRECTORY) {
+            println!("Could not create screenshot directory {}: {}", SCREENSHOT_DIRECTORY, e);
+            return;
+        }
+        match image.save(&path) {
+            Ok(_) => println!("Saved screenshot to {}", path.display()),
+            Err(e) => println!("Could not save screenshot to {}: {}", path.display(), e),
+        }
+    });
+}
+
+
+fn screenshot_path() -> PathBuf{
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    PathBuf::from(SCREENSHOT_DIRECTORY).join(format!("screenshot_{}_{:03}.png", since_epoch.as_secs(), since_epoch.subsec_millis()))
+}
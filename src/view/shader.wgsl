@@ -1,25 +1,57 @@
 // Vertex shader
 
+// has to match CameraUniform in frame.rs
+struct CameraUniform {
+    position: vec2<f32>,
+    zoom: f32,
+    aspect: f32,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// one corner of the unit quad, 0/0 is top left and 1/1 bottom right
 struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) tex_i: u32,
-    @location(2) tex_coords: vec2<f32>,
+    @location(0) corner: vec2<f32>,
+};
+
+// has to match Instance in renderer.rs
+struct InstanceInput {
+    @location(1) position: vec2<f32>,
+    @location(2) size: vec2<f32>,
+    @location(3) tex_i: u32,
+    @location(4) uv_rect: vec4<f32>,
+    @location(5) tint: vec4<f32>,
+    @location(6) rotation: f32,
+    @location(7) pivot: vec2<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_i: u32,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) tint: vec4<f32>,
 };
 
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    out.tex_coords = model.tex_coords;
-    out.clip_position = vec4<f32>(model.position, 1.0);
-    out.tex_i = model.tex_i;
+    // stretch the unit quad over the area of the instance, size is half of the width/height. The pivot ends up at 0/0
+    let local = (model.corner - instance.pivot) * 2.0 * instance.size;
+    // rotate around the pivot, y points down in world space, so a positive angle turns clockwise on the screen
+    let s = sin(instance.rotation);
+    let c = cos(instance.rotation);
+    let world_position = instance.position + vec2<f32>(local.x * c - local.y * s, local.x * s + local.y * c);
+    out.tex_coords = mix(instance.uv_rect.xy, instance.uv_rect.zw, model.corner);
+    // the positions are in world space, move them relative to the camera and scale the visible area to -1..1
+    // in the game logic a higher y is lower on the screen, in wgpu -1 is the bottom, so y gets flipped
+    let half_extent = vec2<f32>(camera.zoom * camera.aspect, camera.zoom);
+    let relative = (world_position - camera.position) / half_extent;
+    out.clip_position = vec4<f32>(relative.x, -relative.y, 0.0, 1.0);
+    out.tex_i = instance.tex_i;
+    out.tint = instance.tint;
     return out;
 }
 
@@ -28,10 +60,10 @@ fn vs_main(
 @group(0) @binding(0)
 var t_diffuse: binding_array<texture_2d<f32>>;
 @group(0) @binding(1)
-var s_diffuse: sampler;
+var s_diffuse: binding_array<sampler>;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let image = t_diffuse[in.tex_i];
-    return textureSample(image, s_diffuse, in.tex_coords);
+    return textureSample(image, s_diffuse[in.tex_i], in.tex_coords) * in.tint;
 }
\ No newline at end of file
@@ -0,0 +1,338 @@
+use std::sync::Arc;
+
+use image::{RgbaImage, Rgba};
+
+use crate::constants::BACKGROUND_COLOR;
+
+use super::{frame::{Frame, CameraUniform, Viewport}, renderer::{Vertex, Instance}, sprite_manifest::{Sprites, SpriteFiltering, SpriteAddressMode}};
+
+
+/**
+ * Draws the same frames as the wgpu renderer, but entirely on the cpu. This is slow, but does not need a gpu, so it can be used in --no-GUI mode and for comparing images
+ * It tries to do exactly what the render pipeline does: the instances are turned into triangles with the same math as the vertex shader,
+ * back faces are culled, textures are sampled with the filtering of the sprite and the result is blended with BlendState::ALPHA_BLENDING
+ * The lights are not drawn into a separate light map, instead every pixel of the viewport is multiplied with the light at its position after the world is drawn
+ * Frame::post_effects are ignored, the image is what the wgpu renderer shows with post processing switched off (F4)
+ */
+pub struct SoftwareRenderer{
+    width: u32,
+    height: u32,
+    sprites: Arc<Sprites>,
+    //most surfaces are sRGB, in that case the gpu converts the (linear) blended colors when writing them. Set this to false to get the raw values
+    srgb_output: bool,
+}
+
+impl SoftwareRenderer {
+
+    pub fn new(width: u32, height: u32, sprites: Arc<Sprites>) -> Self{
+        SoftwareRenderer { width, height, sprites, srgb_output: true }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_srgb_output(mut self, srgb_output: bool) -> Self{
+        self.srgb_output = srgb_output;
+        self
+    }
+
+    pub fn render(&self, frame: &Frame) -> RgbaImage{
+        let mut target = vec![[BACKGROUND_COLOR[0] as f32, BACKGROUND_COLOR[1] as f32, BACKGROUND_COLOR[2] as f32, BACKGROUND_COLOR[3] as f32]; (self.width * self.height) as usize];
+
+        let screen_camera = CameraUniform::screen_space(self.width, self.height);
+        let window = Viewport::full((self.width, self.height));
+        //same order as in the wgpu renderer: the particles are drawn after the instances of RenderLayer::Effects
+        let effects_end = frame.effects_end.min(frame.instances.len());
+        let (below, above) = frame.instances.split_at(effects_end);
+        for instance in below.iter().chain(frame.particles.iter()).chain(above.iter()) {
+            self.draw_instance(&mut target, instance, &frame.camera, &frame.viewport);
+        }
+        self.apply_lights(&mut target, frame);
+        for instance in frame.screen_instances.iter() {
+            self.draw_instance(&mut target, instance, &screen_camera, &window);
+        }
+
+        let mut image = RgbaImage::new(self.width, self.height);
+        for (pixel, color) in image.pixels_mut().zip(target.iter()) {
+            let encode = |channel: f32| if self.srgb_output { linear_to_srgb(channel) } else { channel };
+            *pixel = Rgba([to_u8(encode(color[0])), to_u8(encode(color[1])), to_u8(encode(color[2])), to_u8(color[3])]);
+        }
+        image
+    }
+
+    /**
+     * world space -> pixel coordinates, this is the same calculation as in vs_main, followed by the viewport transformation of the gpu
+     */
+    fn to_pixel(&self, position: [f32; 2], camera: &CameraUniform, viewport: &Viewport) -> [f32; 2]{
+        let half_extent = [camera.zoom * camera.aspect, camera.zoom];
+        let relative = [(position[0] - camera.position[0]) / half_extent[0], (position[1] - camera.position[1]) / half_extent[1]];
+        //clip space y is flipped in the shader and flipped back by the viewport transformation, so relative y can be used directly
+        [viewport.x + (relative[0] + 1.0) * 0.5 * viewport.width, viewport.y + (relative[1] + 1.0) * 0.5 * viewport.height]
+    }
+
+    fn draw_instance(&self, target: &mut [[f32; 4]], instance: &Instance, camera: &CameraUniform, viewport: &Viewport){
+        let vertices = instance.to_vertices();
+        for triangle in vertices.chunks_exact(3) {
+            self.draw_triangle(target, [&triangle[0], &triangle[1], &triangle[2]], camera, viewport);
+        }
+    }
+
+    /**
+     * the composite pass of the LightRenderer: every pixel of the viewport gets multiplied with the ambient light plus all lights that reach it
+     */
+    fn apply_lights(&self, target: &mut [[f32; 4]], frame: &Frame){
+        let camera = &frame.camera;
+        let viewport = &frame.viewport;
+        let half_extent = [camera.zoom * camera.aspect, camera.zoom];
+        let min_x = viewport.x.max(0.0) as u32;
+        let max_x = (viewport.x + viewport.width).min(self.width as f32) as u32;
+        let min_y = viewport.y.max(0.0) as u32;
+        let max_y = (viewport.y + viewport.height).min(self.height as f32) as u32;
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                //inverse of to_pixel, at the pixel center
+                let relative = [(x as f32 + 0.5 - viewport.x) / viewport.width * 2.0 - 1.0, (y as f32 + 0.5 - viewport.y) / viewport.height * 2.0 - 1.0];
+                let world = [camera.position[0] + relative[0] * half_extent[0], camera.position[1] + relative[1] * half_extent[1]];
+                let mut light = [frame.ambient_light[0], frame.ambient_light[1], frame.ambient_light[2]];
+                for light_instance in frame.lights.iter() {
+                    let added = light_instance.light_at(world);
+                    for channel in 0..3 {
+                        light[channel] += added[channel];
+                    }
+                }
+                let destination = &mut target[(y * self.width + x) as usize];
+                for channel in 0..3 {
+                    destination[channel] *= light[channel];
+                }
+            }
+        }
+    }
+
+    fn draw_triangle(&self, target: &mut [[f32; 4]], triangle: [&Vertex; 3], camera: &CameraUniform, viewport: &Viewport){
+        let p = triangle.map(|v| self.to_pixel(v.position, camera, viewport));
+        let area = edge(p[0], p[1], p[2]);
+        //front faces are counter-clockwise on the screen (FrontFace::Ccw), everything else is culled (cull_mode: Back)
+        if area <= 0.0 {
+            return;
+        }
+
+        //everything outside of the viewport is clipped, just like on the gpu
+        let min_x = p.iter().map(|c| c[0]).fold(f32::INFINITY, f32::min).floor().max(viewport.x.max(0.0)) as u32;
+        let max_x = p.iter().map(|c| c[0]).fold(f32::NEG_INFINITY, f32::max).ceil().min((viewport.x + viewport.width).min(self.width as f32)) as u32;
+        let min_y = p.iter().map(|c| c[1]).fold(f32::INFINITY, f32::min).floor().max(viewport.y.max(0.0)) as u32;
+        let max_y = p.iter().map(|c| c[1]).fold(f32::NEG_INFINITY, f32::max).ceil().min((viewport.y + viewport.height).min(self.height as f32)) as u32;
+
+        let sprite = match self.sprites.get(triangle[0].tex_i) {
+            Some(sprite) => sprite,
+            None => return,
+        };
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                //sample at the pixel center, just like the gpu does
+                let point = [x as f32 + 0.5, y as f32 + 0.5];
+                let w0 = edge(p[1], p[2], point);
+                let w1 = edge(p[2], p[0], point);
+                let w2 = edge(p[0], p[1], point);
+                //top left fill rule: pixels exactly on an edge are only drawn for top and left edges, so the two triangles of a quad do not draw the diagonal twice
+                if !is_inside(w0, p[1], p[2]) || !is_inside(w1, p[2], p[0]) || !is_inside(w2, p[0], p[1]) {
+                    continue;
+                }
+                let (w0, w1, w2) = (w0 / area, w1 / area, w2 / area);
+                let u = triangle[0].tex_coords[0] * w0 + triangle[1].tex_coords[0] * w1 + triangle[2].tex_coords[0] * w2;
+                let v = triangle[0].tex_coords[1] * w0 + triangle[1].tex_coords[1] * w1 + triangle[2].tex_coords[1] * w2;
+
+                let texel = sample(&sprite.image, sprite.entry.filtering, sprite.entry.address_mode, u, v);
+                //the tint is the same for all vertices of an instance, so it does not need to be interpolated
+                let tint = triangle[0].tint;
+                let source = [texel[0] * tint[0], texel[1] * tint[1], texel[2] * tint[2], texel[3] * tint[3]];
+                let destination = &mut target[(y * self.width + x) as usize];
+                blend(destination, source);
+            }
+        }
+    }
+}
+
+
+//positive if a, b, c are counter-clockwise on the screen. Pixel coordinates have y pointing down, hence the flipped sign of the usual cross product
+#[inline]
+fn edge(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32{
+    (c[0] - a[0]) * (b[1] - a[1]) - (c[1] - a[1]) * (b[0] - a[0])
+}
+
+//the triangles are counter-clockwise on the screen, so a top edge goes to the left and a left edge goes down
+#[inline]
+fn is_inside(weight: f32, a: [f32; 2], b: [f32; 2]) -> bool{
+    if weight != 0.0 {
+        return weight > 0.0;
+    }
+    let dx = b[0] - a[0];
+    let dy = b[1] - a[1];
+    (dy == 0.0 && dx < 0.0) || dy > 0.0
+}
+
+/**
+ * BlendState::ALPHA_BLENDING: color = src * src_alpha + dst * (1 - src_alpha), alpha = src_alpha + dst_alpha * (1 - src_alpha)
+ */
+#[inline]
+fn blend(destination: &mut [f32; 4], source: [f32; 4]){
+    let alpha = source[3];
+    for channel in 0..3 {
+        destination[channel] = source[channel] * alpha + destination[channel] * (1.0 - alpha);
+    }
+    destination[3] = alpha + destination[3] * (1.0 - alpha);
+}
+
+/**
+ * the textures are uploaded as Rgba8Unorm, so the values are used as they are. Mipmaps are not used here, the image is always sampled at full size
+ */
+fn sample(image: &RgbaImage, filtering: SpriteFiltering, address_mode: SpriteAddressMode, u: f32, v: f32) -> [f32; 4]{
+    let (width, height) = image.dimensions();
+    let texel = |x: i64, y: i64| {
+        let (x, y) = match (address(x, width, address_mode), address(y, height, address_mode)) {
+            (Some(x), Some(y)) => (x, y),
+            //ClampToBorder with a transparent border
+            _ => return [0.0; 4],
+        };
+        let pixel = image.get_pixel(x, y);
+        [pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0, pixel[3] as f32 / 255.0]
+    };
+    let x = u * width as f32;
+    let y = v * height as f32;
+    match filtering {
+        SpriteFiltering::Nearest => texel(x.floor() as i64, y.floor() as i64),
+        SpriteFiltering::Linear => {
+            let x = x - 0.5;
+            let y = y - 0.5;
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let top_left = texel(x0, y0);
+            let top_right = texel(x0 + 1, y0);
+            let bottom_left = texel(x0, y0 + 1);
+            let bottom_right = texel(x0 + 1, y0 + 1);
+            let mut ret = [0.0; 4];
+            for channel in 0..4 {
+                let top = top_left[channel] + (top_right[channel] - top_left[channel]) * fx;
+                let bottom = bottom_left[channel] + (bottom_right[channel] - bottom_left[channel]) * fx;
+                ret[channel] = top + (bottom - top) * fy;
+            }
+            ret
+        }
+    }
+}
+
+/**
+ * maps a texel coordinate that might be outside of the image to one inside, like the address modes of the sampler do. None means the border color
+ */
+#[inline]
+fn address(coordinate: i64, size: u32, address_mode: SpriteAddressMode) -> Option<u32>{
+    let size = size as i64;
+    let ret = match address_mode {
+        SpriteAddressMode::ClampToEdge => coordinate.clamp(0, size - 1),
+        SpriteAddressMode::Repeat => coordinate.rem_euclid(size),
+        SpriteAddressMode::MirrorRepeat => {
+            let period = coordinate.rem_euclid(size * 2);
+            if period < size { period } else { size * 2 - 1 - period }
+        },
+        SpriteAddressMode::ClampToBorder => {
+            if coordinate < 0 || coordinate >= size {
+                return None;
+            }
+            coordinate
+        },
+    };
+    Some(ret as u32)
+}
+
+#[inline]
+fn linear_to_srgb(value: f32) -> f32{
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[inline]
+fn to_u8(value: f32) -> u8{
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use image::{RgbaImage, Rgba};
+
+    use crate::view::{frame::{Frame, CameraUniform, Viewport}, renderer::Instance, sprite_manifest::{Sprites, Sprite, SpriteEntry, SpriteFiltering, SpriteAddressMode}};
+
+    use super::SoftwareRenderer;
+
+    /**
+     * an 8x8 window that shows the world 1:1, an opaque 2x2 checker sprite scaled up to 4x4 in the middle and a half transparent white bar at the top left
+     * srgb output is off, so every expected value can be worked out by hand: the background is 0.2 -> 51, white blended with half alpha over it 0.6 -> 153
+     */
+    #[test]
+    fn renders_golden_frame(){
+        let mut checker = RgbaImage::new(2, 2);
+        checker.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        checker.put_pixel(1, 0, Rgba([0, 255, 0, 255]));
+        checker.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+        checker.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let entry = SpriteEntry {
+            name: "checker".to_string(),
+            path: "checker.png".to_string(),
+            filtering: SpriteFiltering::Nearest,
+            address_mode: SpriteAddressMode::ClampToEdge,
+            mipmaps: false,
+            region: None,
+            glyph: '#',
+        };
+        let sprites = Arc::new(Sprites::from_sprites(vec![Sprite { entry, path: PathBuf::from("checker.png"), image: checker }]));
+
+        let quad = |position: [f32; 2], size: [f32; 2], uv_rect: [f32; 4], tint: [f32; 4]| Instance { position, size, tex_i: 0, uv_rect, tint, rotation: 0.0, pivot: [0.5, 0.5] };
+        let frame = Frame {
+            instances: Arc::new(vec![
+                quad([4.0, 4.0], [2.0, 2.0], [0.0, 0.0, 1.0, 1.0], [1.0; 4]),
+                //only the white texel, 3 pixels wide and 1 high
+                quad([1.5, 0.5], [1.5, 0.5], [0.5, 0.5, 1.0, 1.0], [1.0, 1.0, 1.0, 0.5]),
+            ]),
+            screen_instances: Arc::new(Vec::new()),
+            particles: Arc::new(Vec::new()),
+            effects_end: 2,
+            camera: CameraUniform::screen_space(8, 8),
+            lights: Arc::new(Vec::new()),
+            ambient_light: [1.0; 4],
+            viewport: Viewport::full((8, 8)),
+            post_effects: Arc::new(Vec::new()),
+            screenshot: false,
+        };
+
+        let image = SoftwareRenderer::new(8, 8, sprites).with_srgb_output(false).render(&frame);
+
+        let golden = [
+            "www.....",
+            "........",
+            "..RRGG..",
+            "..RRGG..",
+            "..BBWW..",
+            "..BBWW..",
+            "........",
+            "........",
+        ];
+        for (y, row) in golden.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                let expected = match cell {
+                    '.' => [51, 51, 51, 255],
+                    'w' => [153, 153, 153, 255],
+                    'R' => [255, 0, 0, 255],
+                    'G' => [0, 255, 0, 255],
+                    'B' => [0, 0, 255, 255],
+                    'W' => [255, 255, 255, 255],
+                    _ => unreachable!(),
+                };
+                assert_eq!(image.get_pixel(x as u32, y as u32).0, expected, "pixel {}/{}", x, y);
+            }
+        }
+    }
+}
@@ -1,258 +1,61 @@
 use std::num::NonZeroU32;
 
-use wgpu::{TextureUsages, Device, RenderPipeline, BindGroup, ShaderModule, SurfaceConfiguration};
-
-use crate::view::renderer::Vertex;
-
-pub fn load_sprites(_i: u32, device: &Device, queue: &wgpu::Queue, shader: &ShaderModule, config: &SurfaceConfiguration) -> (RenderPipeline, BindGroup) {
-
-            
-
-            //loading an image from a file
-            let diffuse_bytes = include_bytes!("../../textures/Dwarf_BaseHouse.png");
-            let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-            let diffuse_rgba = diffuse_image.to_rgba8();
-    
-            use image::GenericImageView;
-            let dimensions = diffuse_image.dimensions();
-    
-            let texture_size = wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 1,
-            };
-    
-            let diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
-    
-                label: Some("distinct texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: TextureUsages::COPY_DST | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING,
-                
-            });
-    
-    
-            //this execute a write on the gpu from the loaded image pixel data into our created texture
-            queue.write_texture(
-                // Tells wgpu where to copy the pixel data
-                wgpu::ImageCopyTexture {
-                    texture: &diffuse_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                // The actual pixel data
-                diffuse_rgba.into_raw().as_slice(),
-                // The layout of the texture
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-                    rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-                },
-                texture_size,
-            );
-            // We don't need to configure the texture view much, so let's
-            // let wgpu define it.
-            let dwarf_base_house_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());        //create a handle to access the texture we just created
-        
-    
-    
-            let diffuse_bytes = include_bytes!("../../textures/image_img.png");
-            let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-            let diffuse_rgba = diffuse_image.to_rgba8();
-    
-            let dimensions = diffuse_image.dimensions();
-    
-            let texture_size = wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 1,
-            };
-    
-            let second_diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
-    
-                label: Some("distinct texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: TextureUsages::COPY_DST | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING,
-                
-            });
-            
-            //this execute a write on the gpu from the loaded image pixel data into our created texture
-            queue.write_texture(
-                // Tells wgpu where to copy the pixel data
-                wgpu::ImageCopyTexture {
-                    texture: &second_diffuse_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                // The actual pixel data
-                diffuse_rgba.into_raw().as_slice(),
-                // The layout of the texture
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-                    rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-                },
-                texture_size,
-            );
-            let rust_logo_diffuse_texture_view = second_diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());        //create a handle to access the texture we just created
-
-
-            let diffuse_bytes = include_bytes!("../../textures/background1_cracked_floor.png");
-            let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-            let diffuse_rgba = diffuse_image.to_rgba8();
-    
-            let dimensions = diffuse_image.dimensions();
-    
-            let texture_size = wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 1,
-            };
-    
-            let second_diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
-    
-                label: Some("distinct texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: TextureUsages::COPY_DST | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING,
-                
-            });
-            
-            //this execute a write on the gpu from the loaded image pixel data into our created texture
-            queue.write_texture(
-                // Tells wgpu where to copy the pixel data
-                wgpu::ImageCopyTexture {
-                    texture: &second_diffuse_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                // The actual pixel data
-                diffuse_rgba.into_raw().as_slice(),
-                // The layout of the texture
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-                    rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-                },
-                texture_size,
-            );
-            let background1_cracked_floor_texture_view = second_diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());        //create a handle to access the texture we just created
-
-
-            let diffuse_bytes = include_bytes!("../../textures/background1_cracked_no_floor.png");
-            let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-            let diffuse_rgba = diffuse_image.to_rgba8();
-    
-            let dimensions = diffuse_image.dimensions();
-    
-            let texture_size = wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 1,
-            };
-    
-            let second_diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
-    
-                label: Some("distinct texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: TextureUsages::COPY_DST | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING,
-                
-            });
-            
-            //this execute a write on the gpu from the loaded image pixel data into our created texture
-            queue.write_texture(
-                // Tells wgpu where to copy the pixel data
-                wgpu::ImageCopyTexture {
-                    texture: &second_diffuse_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                // The actual pixel data
-                diffuse_rgba.into_raw().as_slice(),
-                // The layout of the texture
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-                    rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-                },
-                texture_size,
-            );
-            let background1_cracked_no_floor_texture_view = second_diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());        //create a handle to access the texture we just created
-
-            
-    
-    /*      This is another way to load an image, but it is not as easy to use as the one above (not as flexible), tho more slim
-        
-            let diffuse_texture = {
-                let img_data = include_bytes!("../image_img.png");
-                let decoder = png::Decoder::new(std::io::Cursor::new(img_data));
-                let mut reader = decoder.read_info().unwrap();
-                let mut buf = vec![0; reader.output_buffer_size()];
-                let info = reader.next_frame(&mut buf).unwrap();
-    
-                let size = wgpu::Extent3d {
-                    width: info.width,
-                    height: info.height,
-                    depth_or_array_layers: 1,
-                };
-                let texture_format = wgpu::TextureFormat::Rgba8UnormSrgb;
-                let texture = device.create_texture(&wgpu::TextureDescriptor {
-                    label: None,
-                    size,
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: wgpu::TextureDimension::D2,
-                    format: texture_format,
-                    usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                });
-                queue.write_texture(
-                    texture.as_image_copy(),
-                    &buf,
-                    wgpu::ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: std::num::NonZeroU32::new(info.width * 4),
-                        rows_per_image: None,
-                    },
-                    size,
-                );
-                texture
-            };*/
-    
-    
-    
-    
-                    
-            let texture_view_array = [
-                &dwarf_base_house_texture_view,             //tex_i index: 0 
-                &rust_logo_diffuse_texture_view,            //tex_i index: 1
-                &background1_cracked_floor_texture_view,    //tex_i index: 2    
-                &background1_cracked_no_floor_texture_view, //tex_i index: 3
-            ];
+use wgpu::{TextureUsages, Device, RenderPipeline, BindGroup, ShaderModule, SurfaceConfiguration, TextureView, Sampler, BindGroupLayout};
+
+use crate::view::renderer::{QuadVertex, Instance};
+
+use image::{RgbaImage, imageops::{self, FilterType}};
+
+use super::sprite_manifest::{Sprites, Sprite, SpriteFiltering, SpriteAddressMode, SpriteEntry};
+
+/**
+ * The textures and samplers of all sprites and the bind group that puts them into the binding arrays of the shader.
+ * The views and samplers are kept, so a single sprite can be swapped out (see replace) without uploading all the others again
+ */
+pub struct SpriteBindings{
+    layout: BindGroupLayout,
+    texture_views: Vec<TextureView>,
+    samplers: Vec<Sampler>,
+    bind_group: BindGroup,
+}
+
+impl SpriteBindings {
+    pub fn bind_group(&self) -> &BindGroup{
+        &self.bind_group
+    }
+
+    /**
+     * uploads the new image of the sprite at tex_i. The size of the image may change, so a new texture is created and the bind group is rebuilt around it
+     */
+    pub fn replace(&mut self, tex_i: u32, sprite: &Sprite, device: &Device, queue: &wgpu::Queue){
+        let index = tex_i as usize;
+        if index >= self.texture_views.len() {
+            println!("Can't replace sprite {}, there are only {} sprites", tex_i, self.texture_views.len());
+            return;
+        }
+        self.texture_views[index] = upload_sprite(sprite, device, queue);
+        self.samplers[index] = create_sampler(&sprite.entry, device);
+        self.bind_group = create_bind_group(&self.layout, &self.texture_views, &self.samplers, device);
+    }
+}
+
+
+pub fn load_sprites(sprites: &Sprites, device: &Device, queue: &wgpu::Queue, shader: &ShaderModule, config: &SurfaceConfiguration, camera_bind_group_layout: &BindGroupLayout) -> (RenderPipeline, SpriteBindings) {
+
+            //every entry of the sprite manifest gets its own texture and sampler, the index in these arrays is the tex_i used in the vertices
+            let mut texture_views = Vec::with_capacity(sprites.len());
+            let mut samplers = Vec::with_capacity(sprites.len());
+            for sprite in sprites.iter() {
+                texture_views.push(upload_sprite(sprite, device, queue));
+                samplers.push(create_sampler(&sprite.entry, device));
+            }
 
 
             
         //bind groups describe resources that a shaders has access to
         let texture_bind_group_layout =
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[                     //2 Entries: 1st: Texture, 2nd: Sampler for texture
+            entries: &[                     //2 Entries: 1st: Textures, 2nd: Samplers for the textures, both indexed by tex_i
                 wgpu::BindGroupLayoutEntry {    
                     binding: 0,
                     visibility: wgpu::ShaderStages::FRAGMENT,
@@ -261,7 +64,7 @@ pub fn load_sprites(_i: u32, device: &Device, queue: &wgpu::Queue, shader: &Shad
                         view_dimension: wgpu::TextureViewDimension::D2,
                         sample_type: wgpu::TextureSampleType::Float { filterable: true },
                     },
-                    count: NonZeroU32::new(texture_view_array.len() as u32),
+                    count: NonZeroU32::new(texture_views.len() as u32),
                 },
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
@@ -269,46 +72,20 @@ pub fn load_sprites(_i: u32, device: &Device, queue: &wgpu::Queue, shader: &Shad
                     // This should match the filterable field of the
                     // corresponding Texture entry above.
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
+                    count: NonZeroU32::new(samplers.len() as u32),
                 },
             ],
             label: Some("texture_bind_group_layout"),
         });
 
         
-        let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {                                      //a sampler will accept coordinates (X/Y) and return the color data. So this object is asked when the texture is the source of any color operation
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()                        //rest of the fields are initialized with default values
-        });
-
-        //create the actual bind group based on the bind-group-layout. This looks almost identical tho, but it means you could switch these out
-        let diffuse_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureViewArray(&texture_view_array),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
-                    }
-                ],
-                label: Some("diffuse_bind_group"),
-            }
-        );
+        let diffuse_bind_group = create_bind_group(&texture_bind_group_layout, &texture_views, &samplers, device);
 
         
         let render_pipeline_layout =
         device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[&texture_bind_group_layout],
+            bind_group_layouts: &[&texture_bind_group_layout, camera_bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -320,7 +97,8 @@ pub fn load_sprites(_i: u32, device: &Device, queue: &wgpu::Queue, shader: &Shad
                 module: shader,
                 entry_point: "vs_main", // 1.
                 buffers: &[
-                    Vertex::desc(),                                 //insert the vertex buffer that was created above
+                    QuadVertex::desc(),                             //slot 0: the unit quad, slot 1: one Instance per drawn object
+                    Instance::desc(),
                 ], // 2.
             },
             fragment: Some(wgpu::FragmentState { // 3.              //fragment is optional and thus wrapped in Some(), this is needed for storing color on the surface
@@ -352,5 +130,126 @@ pub fn load_sprites(_i: u32, device: &Device, queue: &wgpu::Queue, shader: &Shad
             multiview: None, // 5.
         });
 
-        (render_pipeline, diffuse_bind_group)
-}
\ No newline at end of file
+        (render_pipeline, SpriteBindings { layout: texture_bind_group_layout, texture_views, samplers, bind_group: diffuse_bind_group })
+}
+
+
+//create the actual bind group based on the bind-group-layout. This looks almost identical tho, but it means you could switch these out
+fn create_bind_group(layout: &BindGroupLayout, texture_views: &[TextureView], samplers: &[Sampler], device: &Device) -> BindGroup{
+    let texture_view_array: Vec<&TextureView> = texture_views.iter().collect();
+    let sampler_array: Vec<&Sampler> = samplers.iter().collect();
+    device.create_bind_group(
+        &wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureViewArray(&texture_view_array),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::SamplerArray(&sampler_array),
+                }
+            ],
+            label: Some("diffuse_bind_group"),
+        }
+    )
+}
+
+
+fn upload_sprite(sprite: &Sprite, device: &Device, queue: &wgpu::Queue) -> TextureView{
+    let dimensions = sprite.image.dimensions();
+    let mip_chain = if sprite.entry.mipmaps { generate_mip_chain(&sprite.image) } else { Vec::new() };
+
+    let texture_size = wgpu::Extent3d {
+        width: dimensions.0,
+        height: dimensions.1,
+        depth_or_array_layers: 1,
+    };
+
+    let diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(&sprite.entry.name),
+        size: texture_size,
+        //level 0 is the image itself
+        mip_level_count: 1 + mip_chain.len() as u32,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::COPY_DST | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING,
+    });
+
+    for (mip_level, image) in std::iter::once(&sprite.image).chain(mip_chain.iter()).enumerate() {
+        write_mip_level(&diffuse_texture, mip_level as u32, image, queue);
+    }
+    // We don't need to configure the texture view much, so let's
+    // let wgpu define it.
+    diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default())        //create a handle to access the texture we just created
+}
+
+//this execute a write on the gpu from the loaded image pixel data into our created texture
+fn write_mip_level(texture: &wgpu::Texture, mip_level: u32, image: &RgbaImage, queue: &wgpu::Queue){
+    let dimensions = image.dimensions();
+    queue.write_texture(
+        // Tells wgpu where to copy the pixel data
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        // The actual pixel data
+        image.as_raw().as_slice(),
+        // The layout of the texture
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(4 * dimensions.0),
+            rows_per_image: NonZeroU32::new(dimensions.1),
+        },
+        wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/**
+ * every level is half the size of the previous one (rounded down, at least 1 pixel), down to 1x1. The base image is not part of the returned chain
+ * The levels are made on the cpu once at load time, each one from the previous level, which is good enough for sprites and does not need a compute/render pass
+ */
+fn generate_mip_chain(image: &RgbaImage) -> Vec<RgbaImage>{
+    let mut chain: Vec<RgbaImage> = Vec::new();
+    let (mut width, mut height) = image.dimensions();
+    while width > 1 || height > 1 {
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+        let previous = chain.last().unwrap_or(image);
+        chain.push(imageops::resize(previous, width, height, FilterType::Triangle));
+    }
+    chain
+}
+
+fn create_sampler(entry: &SpriteEntry, device: &Device) -> Sampler{
+    let filter_mode = match entry.filtering {
+        SpriteFiltering::Linear => wgpu::FilterMode::Linear,
+        SpriteFiltering::Nearest => wgpu::FilterMode::Nearest,
+    };
+    let address_mode = match entry.address_mode {
+        SpriteAddressMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        SpriteAddressMode::Repeat => wgpu::AddressMode::Repeat,
+        SpriteAddressMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        //needs Features::ADDRESS_MODE_CLAMP_TO_BORDER, which is requested in renderer_init
+        SpriteAddressMode::ClampToBorder => wgpu::AddressMode::ClampToBorder,
+    };
+    device.create_sampler(&wgpu::SamplerDescriptor {                                      //a sampler will accept coordinates (X/Y) and return the color data. So this object is asked when the texture is the source of any color operation
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        mag_filter: filter_mode,
+        min_filter: filter_mode,
+        //blending between the mip levels only makes sense if there are any, pixel art with mipmaps still gets hard steps between them
+        mipmap_filter: if entry.mipmaps { filter_mode } else { wgpu::FilterMode::Nearest },
+        border_color: if entry.address_mode == SpriteAddressMode::ClampToBorder { Some(wgpu::SamplerBorderColor::TransparentBlack) } else { None },
+        ..Default::default()                        //rest of the fields are initialized with default values
+    })
+}
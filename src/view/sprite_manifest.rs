@@ -0,0 +1,241 @@
+use std::{collections::HashMap, fmt, io, path::{Path, PathBuf}};
+
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::assets;
+
+
+/**
+ * The manifest as it is written in the RON file. The position of an entry in the sprites list is its tex_i index
+ */
+#[derive(Debug, Deserialize)]
+pub struct SpriteManifest{
+    pub sprites: Vec<SpriteEntry>,
+}
+
+impl SpriteManifest {
+    pub fn load(manifest_path: &Path) -> Result<Self, SpriteLoadingError>{
+        let content = assets::read_to_string(manifest_path).map_err(|source| SpriteLoadingError::ManifestRead { path: manifest_path.to_path_buf(), source })?;
+        let manifest: SpriteManifest = ron::from_str(&content).map_err(|source| SpriteLoadingError::ManifestParse { path: manifest_path.to_path_buf(), source })?;
+        if manifest.sprites.is_empty() {
+            return Err(SpriteLoadingError::EmptyManifest { path: manifest_path.to_path_buf() });
+        }
+        Ok(manifest)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SpriteEntry{
+    pub name: String,
+    //relative to the directory of the manifest file
+    pub path: String,
+    #[serde(default)]
+    pub filtering: SpriteFiltering,
+    //what happens with texture coordinates outside of 0..1
+    #[serde(default)]
+    pub address_mode: SpriteAddressMode,
+    //generate smaller versions of the image, so it does not shimmer when zoomed out. Pixel art usually wants this off
+    #[serde(default)]
+    pub mipmaps: bool,
+    //only use a part of the image, e.g. one element of an atlas. If this is None, the whole image is used
+    #[serde(default)]
+    pub region: Option<SpriteRegion>,
+    //how the terminal renderer shows this sprite, the color comes from the texture
+    #[serde(default = "default_glyph")]
+    pub glyph: char,
+}
+
+fn default_glyph() -> char{
+    '#'
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SpriteFiltering{
+    Linear,
+    Nearest,
+}
+
+impl Default for SpriteFiltering {
+    fn default() -> Self {
+        SpriteFiltering::Linear
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SpriteAddressMode{
+    ClampToEdge,
+    Repeat,
+    MirrorRepeat,
+    //transparent outside of the image
+    ClampToBorder,
+}
+
+impl Default for SpriteAddressMode {
+    fn default() -> Self {
+        SpriteAddressMode::ClampToEdge
+    }
+}
+
+//in pixels, origin is the top left corner of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct SpriteRegion{
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+
+#[derive(Debug)]
+pub enum SpriteLoadingError{
+    ManifestRead{ path: PathBuf, source: io::Error },
+    ManifestParse{ path: PathBuf, source: ron::Error },
+    EmptyManifest{ path: PathBuf },
+    DuplicateName{ name: String },
+    ImageRead{ name: String, path: PathBuf, source: io::Error },
+    ImageDecode{ name: String, path: PathBuf, source: image::ImageError },
+    RegionOutOfBounds{ name: String, region: SpriteRegion, dimensions: (u32, u32) },
+}
+
+impl fmt::Display for SpriteLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteLoadingError::ManifestRead { path, source } => write!(f, "could not read sprite manifest {}: {}", path.display(), source),
+            SpriteLoadingError::ManifestParse { path, source } => write!(f, "could not parse sprite manifest {}: {}", path.display(), source),
+            SpriteLoadingError::EmptyManifest { path } => write!(f, "sprite manifest {} does not contain any sprites", path.display()),
+            SpriteLoadingError::DuplicateName { name } => write!(f, "sprite \"{}\" is defined more than once", name),
+            SpriteLoadingError::ImageRead { name, path, source } => write!(f, "could not read image {} for sprite \"{}\": {}", path.display(), name, source),
+            SpriteLoadingError::ImageDecode { name, path, source } => write!(f, "could not decode image {} for sprite \"{}\": {}", path.display(), name, source),
+            SpriteLoadingError::RegionOutOfBounds { name, region, dimensions } => write!(f, "region {:?} of sprite \"{}\" does not fit into the image ({}x{})", region, name, dimensions.0, dimensions.1),
+        }
+    }
+}
+
+impl std::error::Error for SpriteLoadingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SpriteLoadingError::ManifestRead { source, .. } => Some(source),
+            SpriteLoadingError::ManifestParse { source, .. } => Some(source),
+            SpriteLoadingError::ImageRead { source, .. } => Some(source),
+            SpriteLoadingError::ImageDecode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+
+pub struct Sprite{
+    pub entry: SpriteEntry,
+    //where the image was loaded from, entry.path resolved relative to the manifest
+    pub path: PathBuf,
+    pub image: RgbaImage,
+}
+
+impl Sprite {
+    /**
+     * decodes the image of the sprite again, e.g. because the file changed on disk
+     */
+    pub fn reload(&self) -> Result<Sprite, SpriteLoadingError>{
+        let image = load_image(&self.path, &self.entry)?;
+        Ok(Sprite { entry: self.entry.clone(), path: self.path.clone(), image })
+    }
+}
+
+/**
+ * All sprites of the manifest, decoded and ready to be uploaded. This is loaded once at startup and shared between the model (for the name -> tex_i lookup) and the view
+ */
+pub struct Sprites{
+    sprites: Vec<Sprite>,
+    ids: HashMap<String, u32>,
+}
+
+impl Sprites {
+
+    pub fn load(manifest_path: &Path) -> Result<Self, SpriteLoadingError>{
+        let manifest = SpriteManifest::load(manifest_path)?;
+        let base_dir = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut sprites = Vec::with_capacity(manifest.sprites.len());
+        let mut ids = HashMap::with_capacity(manifest.sprites.len());
+        for (tex_i, entry) in manifest.sprites.into_iter().enumerate() {
+            if ids.insert(entry.name.clone(), tex_i as u32).is_some() {
+                return Err(SpriteLoadingError::DuplicateName { name: entry.name });
+            }
+            let path = base_dir.join(&entry.path);
+            let image = load_image(&path, &entry)?;
+            sprites.push(Sprite { entry, path, image });
+        }
+
+        Ok(Sprites { sprites, ids })
+    }
+
+    /**
+     * the tex_i index of the sprite with the given name. Panics if the manifest does not contain it, since this is a programming error that should be caught right away
+     */
+    pub fn id(&self, name: &str) -> u32{
+        match self.try_id(name) {
+            Some(tex_i) => tex_i,
+            None => panic!("There is no sprite called \"{}\" in the sprite manifest!", name),
+        }
+    }
+
+    pub fn try_id(&self, name: &str) -> Option<u32>{
+        self.ids.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize{
+        self.sprites.len()
+    }
+
+    /**
+     * width and height in pixels of the (cropped) image of the sprite
+     */
+    pub fn dimensions(&self, tex_i: u32) -> (u32, u32){
+        self.sprites[tex_i as usize].image.dimensions()
+    }
+
+    /**
+     * width / height, objects should use this to not stretch their texture
+     */
+    pub fn aspect_ratio(&self, tex_i: u32) -> f32{
+        let (width, height) = self.dimensions(tex_i);
+        width as f32 / height as f32
+    }
+
+    pub fn get(&self, tex_i: u32) -> Option<&Sprite>{
+        self.sprites.get(tex_i as usize)
+    }
+
+    /**
+     * sprites that do not come from a manifest, the tex_i of every sprite is its position in the vec
+     */
+    #[cfg(test)]
+    pub(crate) fn from_sprites(sprites: Vec<Sprite>) -> Self{
+        let ids = sprites.iter().enumerate().map(|(tex_i, sprite)| (sprite.entry.name.clone(), tex_i as u32)).collect();
+        Sprites { sprites, ids }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Sprite>{
+        self.sprites.iter()
+    }
+}
+
+
+fn load_image(path: &Path, entry: &SpriteEntry) -> Result<RgbaImage, SpriteLoadingError>{
+    let bytes = assets::read(path).map_err(|source| SpriteLoadingError::ImageRead { name: entry.name.clone(), path: path.to_path_buf(), source })?;
+    let image = image::load_from_memory(&bytes).map_err(|source| SpriteLoadingError::ImageDecode { name: entry.name.clone(), path: path.to_path_buf(), source })?;
+    let image = image.to_rgba8();
+
+    match entry.region {
+        None => Ok(image),
+        Some(region) => {
+            let dimensions = image.dimensions();
+            if region.width == 0 || region.height == 0 || region.x.checked_add(region.width).map_or(true, |end| end > dimensions.0)
+                || region.y.checked_add(region.height).map_or(true, |end| end > dimensions.1) {
+                return Err(SpriteLoadingError::RegionOutOfBounds { name: entry.name.clone(), region, dimensions });
+            }
+            Ok(image::imageops::crop_imm(&image, region.x, region.y, region.width, region.height).to_image())
+        }
+    }
+}
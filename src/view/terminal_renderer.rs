@@ -0,0 +1,369 @@
+use std::{thread::{self, JoinHandle}, sync::{Arc, atomic::{AtomicBool, Ordering}}, time::{Instant, Duration}, collections::HashMap, io::{self, Read, Write}, process::{Command, Stdio}, fmt::Write as _};
+
+use flume::{Sender, Receiver};
+use tokio::{runtime::Handle, time::MissedTickBehavior};
+use winit::event::{VirtualKeyCode, ElementState};
+
+use crate::{controller::{controller_input::ControllerInput, input_script::parse_key}, launch_options::LaunchOptions, signals};
+
+use super::{frame::Frame, sprite_manifest::Sprites, render_statistics::RenderStatistics, renderer::Instance, dummy_renderer};
+
+//a terminal can't show much more than this anyway, and every frame is a few kilobytes of escape codes
+const TERMINAL_RATE: f64 = 20.0;
+//width / height of one character cell, most terminal fonts are about twice as tall as wide
+const CELL_ASPECT: f32 = 0.5;
+//terminals only report key presses, so a key counts as held until it was not repeated for this long. The first repeat takes longer than the following ones
+const FIRST_HOLD: Duration = Duration::from_millis(600);
+const REPEAT_HOLD: Duration = Duration::from_millis(150);
+//asking stty for the size starts a process, so the size is only checked every so often
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+//cells with less coverage than this show what is behind them
+const ALPHA_THRESHOLD: f32 = 0.5;
+
+//a lone ESC is only the escape key if nothing followed it for this long, otherwise it could be the start of an arrow key that was split between two reads
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(100);
+
+const CTRL_C: u8 = 3;
+const ESCAPE: u8 = 27;
+
+
+#[derive(Clone, Copy)]
+struct Cell{
+    glyph: char,
+    color: [f32; 3],
+}
+
+
+/**
+ * Draws frames as characters into a terminal, for looking at the world over ssh. Every cell shows the topmost instance at its center:
+ * the glyph of its sprite (SpriteEntry::glyph) in the color of the texel there, multiplied with the tint and the light
+ * Screen space instances (the debug overlay) are left out, a status line below the world shows the most important numbers instead
+ */
+pub struct TerminalRenderer{
+    sprites: Arc<Sprites>,
+}
+
+impl TerminalRenderer {
+
+    pub fn new(sprites: Arc<Sprites>) -> Self{
+        TerminalRenderer { sprites }
+    }
+
+    /**
+     * the world as escape codes for a terminal of columns x rows cells, centered and with the aspect ratio of the camera
+     */
+    pub fn render(&self, frame: &Frame, columns: usize, rows: usize) -> String{
+        let camera = &frame.camera;
+        let (width, height) = fit_grid(camera.aspect, columns, rows);
+        let mut cells: Vec<Option<Cell>> = vec![None; width * height];
+        let half_extent = [camera.zoom * camera.aspect, camera.zoom];
+        //cell -> world position of its center, the inverse of the camera transformation in vs_main
+        let to_world = |x: usize, y: usize| [
+            camera.position[0] + ((x as f32 + 0.5) / width as f32 * 2.0 - 1.0) * half_extent[0],
+            camera.position[1] + ((y as f32 + 0.5) / height as f32 * 2.0 - 1.0) * half_extent[1],
+        ];
+        //world position -> fractional cell
+        let to_cell = |position: [f32; 2]| [
+            ((position[0] - camera.position[0]) / half_extent[0] + 1.0) * 0.5 * width as f32,
+            ((position[1] - camera.position[1]) / half_extent[1] + 1.0) * 0.5 * height as f32,
+        ];
+
+        //same order as the other renderers: the particles are drawn after the instances of RenderLayer::Effects
+        let effects_end = frame.effects_end.min(frame.instances.len());
+        let (below, above) = frame.instances.split_at(effects_end);
+        for instance in below.iter().chain(frame.particles.iter()).chain(above.iter()) {
+            let corners = instance.to_vertices().map(|vertex| to_cell(vertex.position));
+            let min_x = corners.iter().map(|c| c[0]).fold(f32::INFINITY, f32::min).floor().max(0.0) as usize;
+            let max_x = corners.iter().map(|c| c[0]).fold(f32::NEG_INFINITY, f32::max).ceil().min(width as f32).max(0.0) as usize;
+            let min_y = corners.iter().map(|c| c[1]).fold(f32::INFINITY, f32::min).floor().max(0.0) as usize;
+            let max_y = corners.iter().map(|c| c[1]).fold(f32::NEG_INFINITY, f32::max).ceil().min(height as f32).max(0.0) as usize;
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    if let Some(cell) = self.sample(instance, to_world(x, y)) {
+                        cells[y * width + x] = Some(cell);
+                    }
+                }
+            }
+        }
+
+        let mut output = String::with_capacity(width * height * 4);
+        let offset = ((columns - width) / 2, (rows - height) / 2);
+        let mut last_color = None;
+        for y in 0..height {
+            //escape codes count from 1
+            write!(output, "\x1b[{};{}H", offset.1 + y + 1, offset.0 + 1).unwrap();
+            for x in 0..width {
+                match cells[y * width + x] {
+                    Some(cell) => {
+                        let light = light_at(frame, to_world(x, y));
+                        let color = [0, 1, 2].map(|channel| (cell.color[channel] * light[channel]).clamp(0.0, 1.0));
+                        let color = color.map(|channel| (channel * 255.0).round() as u8);
+                        if last_color != Some(color) {
+                            write!(output, "\x1b[38;2;{};{};{}m", color[0], color[1], color[2]).unwrap();
+                            last_color = Some(color);
+                        }
+                        output.push(cell.glyph);
+                    },
+                    None => output.push(' '),
+                }
+            }
+        }
+        output.push_str("\x1b[0m");
+        output
+    }
+
+    /**
+     * what the instance looks like at the given world position, None if the position is outside of it or the texture is transparent there
+     */
+    fn sample(&self, instance: &Instance, position: [f32; 2]) -> Option<Cell>{
+        if instance.size[0] <= 0.0 || instance.size[1] <= 0.0 {
+            return None;
+        }
+        //the inverse of Instance::to_vertices: move to the pivot, rotate back and scale to the unit quad
+        let (sin, cos) = instance.rotation.sin_cos();
+        let offset = [position[0] - instance.position[0], position[1] - instance.position[1]];
+        let local = [offset[0] * cos + offset[1] * sin, -offset[0] * sin + offset[1] * cos];
+        let corner = [local[0] / (2.0 * instance.size[0]) + instance.pivot[0], local[1] / (2.0 * instance.size[1]) + instance.pivot[1]];
+        if !(0.0..=1.0).contains(&corner[0]) || !(0.0..=1.0).contains(&corner[1]) {
+            return None;
+        }
+        let sprite = self.sprites.get(instance.tex_i)?;
+        let uv = [
+            instance.uv_rect[0] + (instance.uv_rect[2] - instance.uv_rect[0]) * corner[0],
+            instance.uv_rect[1] + (instance.uv_rect[3] - instance.uv_rect[1]) * corner[1],
+        ];
+        //nearest texel, one cell is much bigger than a texel anyway
+        let (image_width, image_height) = sprite.image.dimensions();
+        let texel_x = ((uv[0].rem_euclid(1.0) * image_width as f32) as u32).min(image_width - 1);
+        let texel_y = ((uv[1].rem_euclid(1.0) * image_height as f32) as u32).min(image_height - 1);
+        let texel = sprite.image.get_pixel(texel_x, texel_y).0;
+        if texel[3] as f32 / 255.0 * instance.tint[3] < ALPHA_THRESHOLD {
+            return None;
+        }
+        Some(Cell {
+            glyph: sprite.entry.glyph,
+            color: [0, 1, 2].map(|channel| texel[channel] as f32 / 255.0 * instance.tint[channel]),
+        })
+    }
+}
+
+
+/**
+ * the biggest grid of cells that fits into columns x rows and shows the world with the given aspect ratio (width / height)
+ */
+fn fit_grid(aspect: f32, columns: usize, rows: usize) -> (usize, usize){
+    let width_for_rows = (rows as f32 * aspect / CELL_ASPECT).round() as usize;
+    if width_for_rows <= columns {
+        (width_for_rows.max(1), rows.max(1))
+    } else {
+        (columns.max(1), ((columns as f32 * CELL_ASPECT / aspect).round() as usize).clamp(1, rows.max(1)))
+    }
+}
+
+fn light_at(frame: &Frame, position: [f32; 2]) -> [f32; 3]{
+    let mut light = [frame.ambient_light[0], frame.ambient_light[1], frame.ambient_light[2]];
+    for light_instance in frame.lights.iter() {
+        let added = light_instance.light_at(position);
+        for channel in 0..3 {
+            light[channel] += added[channel];
+        }
+    }
+    light
+}
+
+
+/**
+ * runs stty on the terminal the game was started from, None if that did not work (e.g. stdin is not a terminal)
+ */
+fn stty(args: &[&str]) -> Option<String>{
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+    if output.status.success() { Some(String::from_utf8_lossy(&output.stdout).trim().to_string()) } else { None }
+}
+
+/**
+ * (columns, rows) of the terminal
+ */
+fn terminal_size() -> Option<(usize, usize)>{
+    let size = stty(&["size"])?;
+    let mut parts = size.split_whitespace().map(|part| part.parse::<usize>().ok());
+    match (parts.next()?, parts.next()?) {
+        (Some(rows), Some(columns)) if rows > 1 && columns > 0 => Some((columns, rows)),
+        _ => None,
+    }
+}
+
+/**
+ * what the bytes starting with an ESC are
+ */
+enum Escape{
+    //a complete escape sequence (ESC [ ... or ESC O ...) of this many bytes, e.g. an arrow key
+    Sequence(usize),
+    //the rest might still be on its way
+    Incomplete,
+    //the escape key, followed by something that does not belong to it
+    Key,
+}
+
+fn read_escape(bytes: &[u8]) -> Escape{
+    match bytes.get(1) {
+        None => Escape::Incomplete,
+        Some(b'[') | Some(b'O') => match bytes[2..].iter().position(|byte| byte.is_ascii_alphabetic() || *byte == b'~') {
+            Some(end) => Escape::Sequence(end + 3),
+            None => Escape::Incomplete,
+        },
+        Some(_) => Escape::Key,
+    }
+}
+
+/**
+ * switches the terminal to reading single key presses without echo and to the alternate screen. Everything is restored when this is dropped, also on panics
+ */
+struct TerminalGuard{
+    saved_settings: String,
+}
+
+impl TerminalGuard {
+    fn enter() -> Option<Self>{
+        let saved_settings = stty(&["-g"])?;
+        //-isig: ctrl+c arrives as a byte and stops the game properly instead of killing it with the terminal still in raw mode
+        //min 0 time 1: reading returns after a tenth of a second even if nothing was typed, so the input thread can notice the game stopping
+        stty(&["-icanon", "-echo", "-isig", "min", "0", "time", "1"])?;
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        let _ = io::stdout().flush();
+        Some(TerminalGuard { saved_settings })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        stty(&[&self.saved_settings]);
+    }
+}
+
+
+/**
+ * The terminal frontend, --terminal. Like the headless runner, but every frame is drawn into the terminal the game was started from
+ * Typed keys go through the controller like the keys of the window (WASD pans, I/O zoom, ...), q, Esc or ctrl+c stop the game, so does SIGTERM
+ */
+pub(crate) async fn go( mut threads_vec: Vec<JoinHandle<()>>, running: Arc<AtomicBool>, controller_sender: Sender<ControllerInput>, vertex_receiver: Receiver<Frame>, sprites: Arc<Sprites>, render_statistics: Arc<RenderStatistics>, options: LaunchOptions, rt: Handle){
+    let guard = match TerminalGuard::enter() {
+        Some(guard) => guard,
+        None => {
+            eprintln!("Could not set up the terminal (is stdin a terminal?), continuing as if --no-GUI was given");
+            return dummy_renderer::go(threads_vec, running, controller_sender, vertex_receiver, sprites, render_statistics, options, rt).await;
+        }
+    };
+    let renderer = TerminalRenderer::new(sprites);
+    signals::stop_on_signal(&rt, running.clone());
+
+    //reading stdin blocks, so it gets its own thread. Reads time out (see TerminalGuard::enter), so it stops with the others and is joined by shutdown
+    let (key_sender, key_receiver) = flume::unbounded::<u8>();
+    let input_running = running.clone();
+    let input_thread = thread::Builder::new().name("terminal input".to_string()).spawn(move || {
+        let mut stdin = io::stdin().lock();
+        let mut buffer = [0; 64];
+        while input_running.load(Ordering::Relaxed) {
+            match stdin.read(&mut buffer) {
+                //nothing was typed within the timeout
+                Ok(0) => continue,
+                Ok(length) => if buffer[..length].iter().any(|byte| key_sender.send(*byte).is_err()) { break },
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+    }).expect("Could not spawn the terminal input thread");
+    threads_vec.push(input_thread);
+
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / TERMINAL_RATE));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    //when each held key was last typed and whether it was repeated since it was pressed
+    let mut held_keys: HashMap<VirtualKeyCode, (Instant, bool)> = HashMap::new();
+    let mut size = terminal_size().unwrap_or((80, 24));
+    let mut last_size_check = Instant::now();
+    let mut last_frame = Instant::now();
+    //typed bytes that were not handled yet, only ever an unfinished escape sequence is left over after a frame
+    let mut bytes: Vec<u8> = Vec::new();
+    //when the unfinished escape sequence at the start of bytes was first seen
+    let mut escape_since: Option<Instant> = None;
+    'frames: while running.load(Ordering::Relaxed){
+        ticker.tick().await;
+
+        bytes.extend(key_receiver.try_iter());
+        let mut i = 0;
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if byte == ESCAPE {
+                match read_escape(&bytes[i..]) {
+                    //arrow keys and friends are escape sequences, they are not bound to anything, so they are skipped
+                    Escape::Sequence(length) => {
+                        i += length;
+                        escape_since = None;
+                        continue;
+                    },
+                    Escape::Incomplete if escape_since.get_or_insert_with(Instant::now).elapsed() < ESCAPE_TIMEOUT => break,
+                    //the escape key itself
+                    Escape::Incomplete | Escape::Key => break 'frames,
+                }
+            }
+            i += 1;
+            if byte == CTRL_C || byte == b'q' {
+                break 'frames;
+            }
+            let key = match parse_key(&(byte as char).to_string()) {
+                Some(key) => key,
+                None => continue,
+            };
+            match held_keys.get_mut(&key) {
+                Some((last_typed, repeated)) => {
+                    *last_typed = Instant::now();
+                    *repeated = true;
+                },
+                None => {
+                    held_keys.insert(key, (Instant::now(), false));
+                    let _ = controller_sender.send(ControllerInput::KeyboardInput { key: Some(key), state: ElementState::Pressed });
+                },
+            }
+        }
+        bytes.drain(..i);
+        held_keys.retain(|key, (last_typed, repeated)| {
+            let hold = if *repeated { REPEAT_HOLD } else { FIRST_HOLD };
+            if last_typed.elapsed() < hold {
+                return true;
+            }
+            let _ = controller_sender.send(ControllerInput::KeyboardInput { key: Some(*key), state: ElementState::Released });
+            false
+        });
+
+        let frame = match vertex_receiver.recv_async().await {
+            Ok(frame) => frame,
+            Err(_) => break,
+        };
+        render_statistics.record_frame(last_frame.elapsed());
+        last_frame = Instant::now();
+
+        if last_size_check.elapsed() >= SIZE_CHECK_INTERVAL {
+            let new_size = terminal_size().unwrap_or(size);
+            if new_size != size {
+                print!("\x1b[2J");
+            }
+            size = new_size;
+            last_size_check = Instant::now();
+        }
+        //the last row is the status line
+        let mut output = renderer.render(&frame, size.0, size.1 - 1);
+        let status = format!("camera ({:.2}, {:.2})  instances {}  particles {}  lights {}  {:.0} fps | WASD pan, I/O zoom, q quit",
+            frame.camera.position[0], frame.camera.position[1], frame.instances.len(), frame.particles.len(), frame.lights.len(), render_statistics.frames_per_second());
+        write!(output, "\x1b[{};1H\x1b[2K{}", size.1, status.chars().take(size.0).collect::<String>()).unwrap();
+        let mut stdout = io::stdout().lock();
+        if stdout.write_all(output.as_bytes()).and_then(|_| stdout.flush()).is_err() {
+            break;
+        }
+    }
+
+    drop(guard);
+    if !dummy_renderer::shutdown(&mut threads_vec, &running, controller_sender, &vertex_receiver) {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{sync::{Arc, atomic::{AtomicBool, Ordering}}, thread::{self, JoinHandle}, time::{Duration, SystemTime}, collections::HashMap, path::{Path, PathBuf}, fs};
+
+use flume::{Receiver, Sender};
+
+use crate::assets;
+
+use super::sprite_manifest::{Sprites, Sprite, SpriteManifest};
+
+
+//how often the directory is checked for changes. Checking the modification times is cheap, decoding only happens for changed files
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+
+/**
+ * a sprite whose file or manifest entry changed on disk, decoded and ready to be uploaded in place of the old one
+ */
+pub struct TextureReload{
+    pub tex_i: u32,
+    pub sprite: Sprite,
+}
+
+
+/**
+ * Dev mode only: watches the directory of the sprite manifest (and everything below it) on another thread and sends the sprites that changed, so the render thread can upload them
+ * without restarting the game. The modification times of all files are polled, which works the same on every platform and needs no extra dependency
+ * A changed image reloads every sprite that uses it, a changed manifest reloads the sprites whose entry changed (path, region, filtering, ...).
+ * New sprites in the manifest and new files are only reported, every sprite has a fixed slot in the binding array, so adding one needs a restart
+ * Only the textures are replaced, objects that were sized after the old aspect ratio keep their size
+ * The thread stops within POLL_INTERVAL once running is cleared, the handle should be joined with the other threads
+ */
+pub fn spawn(manifest_path: &Path, sprites: &Sprites, running: Arc<AtomicBool>) -> (JoinHandle<()>, Receiver<TextureReload>){
+    let (sender, receiver) = flume::unbounded();
+    let mut watcher = TextureWatcher {
+        manifest_path: manifest_path.to_path_buf(),
+        directory: manifest_path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf(),
+        sprites: sprites.iter().map(|sprite| Sprite { entry: sprite.entry.clone(), path: sprite.path.clone(), image: Default::default() }).collect(),
+        modified: HashMap::new(),
+        sender,
+    };
+    watcher.modified = watcher.scan();
+
+    let handle = thread::Builder::new().name("texture watcher".to_string()).spawn(move || {
+        println!("Watching {} for changes of {} sprites", watcher.directory.display(), watcher.sprites.len());
+        //stops as soon as the game shuts down or the renderer is gone
+        while running.load(Ordering::Relaxed) && !watcher.sender.is_disconnected() {
+            thread::sleep(POLL_INTERVAL);
+            if !watcher.poll() {
+                return;
+            }
+        }
+    }).expect("Could not spawn the texture watcher thread");
+    (handle, receiver)
+}
+
+
+struct TextureWatcher{
+    manifest_path: PathBuf,
+    directory: PathBuf,
+    //the sprites as they are currently uploaded, without their images
+    sprites: Vec<Sprite>,
+    //every file below directory
+    modified: HashMap<PathBuf, SystemTime>,
+    sender: Sender<TextureReload>,
+}
+
+impl TextureWatcher {
+    /**
+     * looks for changed files once and sends the sprites that need to be uploaded again. Returns false if the renderer is gone
+     */
+    fn poll(&mut self) -> bool{
+        let current = self.scan();
+        let mut changed_files: Vec<&PathBuf> = Vec::new();
+        for (path, modified) in current.iter() {
+            match self.modified.get(path) {
+                Some(last_modified) if last_modified == modified => (),
+                Some(_) => changed_files.push(path),
+                None => {
+                    //a new file that a sprite already points to was probably just written again (some editors delete and recreate the file when saving)
+                    if self.sprites.iter().any(|sprite| &sprite.path == path) {
+                        changed_files.push(path);
+                    } else if *path != self.manifest_path {
+                        println!("New file {}, add it to the sprite manifest and restart to use it", path.display());
+                    }
+                },
+            }
+        }
+
+        let mut to_reload: Vec<usize> = Vec::new();
+        if changed_files.contains(&&self.manifest_path) {
+            to_reload.extend(self.apply_manifest());
+        }
+        for (tex_i, sprite) in self.sprites.iter().enumerate() {
+            if changed_files.contains(&&sprite.path) && !to_reload.contains(&tex_i) {
+                to_reload.push(tex_i);
+            }
+        }
+        //even if a file can't be decoded (e.g. because it is only half written), the next change is going to be picked up again
+        self.modified = current;
+
+        for tex_i in to_reload {
+            let sprite = &self.sprites[tex_i];
+            match sprite.reload() {
+                Ok(reloaded) => {
+                    println!("Reloading sprite \"{}\" from {}", sprite.entry.name, sprite.path.display());
+                    if self.sender.send(TextureReload { tex_i: tex_i as u32, sprite: reloaded }).is_err() {
+                        return false;
+                    }
+                },
+                Err(e) => println!("Could not reload sprite: {}", e),
+            }
+        }
+        true
+    }
+
+    /**
+     * reads the manifest again and takes over the entries that changed. Returns the tex_i of every sprite that has to be reloaded because of that
+     */
+    fn apply_manifest(&mut self) -> Vec<usize>{
+        let manifest = match SpriteManifest::load(&self.manifest_path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                println!("Could not reload the sprite manifest: {}", e);
+                return Vec::new();
+            }
+        };
+        if manifest.sprites.len() != self.sprites.len() {
+            println!("The sprite manifest now has {} sprites instead of {}, restart to add or remove sprites", manifest.sprites.len(), self.sprites.len());
+        }
+        let mut changed = Vec::new();
+        for (tex_i, (sprite, entry)) in self.sprites.iter_mut().zip(manifest.sprites).enumerate() {
+            if sprite.entry == entry {
+                continue;
+            }
+            //the model looks sprites up by name once, a sprite that got another name or position would end up on the wrong objects
+            if sprite.entry.name != entry.name {
+                println!("Sprite {} is now called \"{}\" instead of \"{}\", restart to rename or reorder sprites", tex_i, entry.name, sprite.entry.name);
+                continue;
+            }
+            sprite.path = self.directory.join(&entry.path);
+            sprite.entry = entry;
+            changed.push(tex_i);
+        }
+        changed
+    }
+
+    /**
+     * the modification times of all files below the directory
+     */
+    fn scan(&self) -> HashMap<PathBuf, SystemTime>{
+        let mut files = HashMap::new();
+        let mut directories = vec![self.directory.clone()];
+        while let Some(directory) = directories.pop() {
+            let entries = match fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if entry.file_type().map_or(false, |file_type| file_type.is_dir()) {
+                    directories.push(path);
+                } else if let Some(modified) = assets::modified(&path) {
+                    files.insert(path, modified);
+                }
+            }
+        }
+        files
+    }
+}